@@ -0,0 +1,115 @@
+//! Compares rendering help files to HTML by joining the output of the entire tree in memory
+//! against writing the output of each block as it is converted.
+//!
+//! Usage: `cargo bench --bench render -- <FILE>...`, e.g. with Neovim's `runtime/doc/builtin.txt`
+//! and `runtime/doc/api.txt`.
+//!
+//! The joined column measures rendering as it was done before output was streamed, where the
+//! output of every block is joined into one string for the entire help file. The streamed column
+//! measures [`Converter::write_to`], which converts the same blocks but writes each of them before
+//! converting the next, so a single run prints the before and after times and peak heap usage side
+//! by side for each file. Both convert each block the same way, so the difference is mostly in
+//! peak heap usage rather than time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use vimdoc2html::*;
+
+/// Number of times each help file is rendered, keeping the fastest time.
+const ITERATIONS: usize = 10;
+
+/// Allocator that tracks the peak number of bytes allocated on the heap.
+struct PeakAlloc {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = self.current.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            self.peak.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        self.current.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc {
+    current: AtomicUsize::new(0),
+    peak: AtomicUsize::new(0),
+};
+
+/// Runs `f` repeatedly, returning the fastest time and the most heap allocated at once beyond
+/// what was allocated before running.
+fn measure(mut f: impl FnMut()) -> (Duration, usize) {
+    let mut fastest = Duration::MAX;
+    let mut peak = 0;
+    for _ in 0..ITERATIONS {
+        let baseline = ALLOC.current.load(Ordering::Relaxed);
+        ALLOC.peak.store(baseline, Ordering::Relaxed);
+
+        let start = Instant::now();
+        f();
+        fastest = fastest.min(start.elapsed());
+        peak = peak.max(ALLOC.peak.load(Ordering::Relaxed) - baseline);
+    }
+    (fastest, peak)
+}
+
+fn main() {
+    // Arguments like --bench are passed by cargo and are not files
+    let paths: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    if paths.is_empty() {
+        eprintln!("Usage: cargo bench --bench render -- <FILE>...");
+        return;
+    }
+
+    println!(
+        "{:<24} {:>12} {:>12} {:>12} {:>12}",
+        "file", "joined", "joined mem", "streamed", "streamed mem"
+    );
+    for path in paths {
+        let parser = Parser::load_vimdoc_file(&path).expect("Failed to load help file");
+
+        let (joined, joined_mem) = measure(|| {
+            let mut converter = Converter::new(HtmlTranslator::new(Default::default()));
+            let mut cursor = parser.tree().walk();
+            let html = converter.visit(&mut Context::new(parser.src(), &mut cursor));
+            io::Write::write_all(&mut io::sink(), html.as_bytes()).unwrap();
+        });
+        let (streamed, streamed_mem) = measure(|| {
+            let mut converter = Converter::new(HtmlTranslator::new(Default::default()));
+            let mut cursor = parser.tree().walk();
+            converter
+                .write_to(
+                    &mut Context::new(parser.src(), &mut cursor),
+                    &mut io::sink(),
+                )
+                .unwrap();
+        });
+
+        let name = std::path::Path::new(&path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        println!(
+            "{name:<24} {:>10.2?} {:>10}KB {:>10.2?} {:>10}KB",
+            joined,
+            joined_mem / 1024,
+            streamed,
+            streamed_mem / 1024,
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod parser;
+mod utils;
+
+pub use parser::*;
+pub use utils::{escape_html, url_encode_path};
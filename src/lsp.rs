@@ -0,0 +1,779 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use vimdoc2html::*;
+
+use super::serve::percent_decode;
+use super::{find_files, CommandResult, Failures};
+
+/// Error code of a request whose method is not supported.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Error code of a request with missing or invalid parameters.
+const INVALID_PARAMS: i64 = -32602;
+
+/// Error code of a message that is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// Most tags offered when completing a taglink, beyond which the client is told the list is
+/// incomplete so it asks again as more of the tag is typed.
+const MAX_COMPLETIONS: usize = 500;
+
+/// Most lines of a tagged section shown when hovering over a link to its tag.
+const MAX_HOVER_LINES: usize = 30;
+
+/// Runs a language server over stdin and stdout until the client asks it to exit.
+pub fn run() -> CommandResult {
+    let code = run_with(io::stdin().lock(), io::stdout().lock())?;
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// Runs a language server that reads messages from `input` and writes messages to `output` until
+/// the client asks it to exit or closes the `input`. Returns the exit code requested by the
+/// protocol, which is an error if the client did not ask the server to shut down before exiting.
+pub fn run_with(
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut server = Server::new()?;
+
+    while let Some(message) = read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(x) => {
+                let error = RpcError::new(PARSE_ERROR, x.to_string());
+                write_message(&mut output, &error.into_response(Value::Null))?;
+                continue;
+            }
+        };
+
+        // Responses to requests made by the server have no method and are ignored
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            continue;
+        };
+        if method == "exit" {
+            return Ok(if server.is_shutdown { 0 } else { 1 });
+        }
+
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = server.handle(method, params);
+
+        // Notifications have no id and expect no response, even when they fail
+        if let Some(id) = message.get("id").cloned() {
+            let response = match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(x) => x.into_response(id),
+            };
+            write_message(&mut output, &response)?;
+        }
+        for notification in server.notifications.drain(..) {
+            write_message(&mut output, &notification)?;
+        }
+    }
+
+    Ok(0)
+}
+
+/// Reads the next message sent by the client, or none if the client closed the stream. Messages
+/// that are not valid JSON are returned as errors so they can be reported to the client, while
+/// messages without a `Content-Length` header cannot be read and are skipped.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<serde_json::Result<Value>>> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if input.read_line(&mut header)? == 0 {
+                return Ok(None);
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+
+        if let Some(length) = length {
+            let mut body = vec![0; length];
+            input.read_exact(&mut body)?;
+            return Ok(Some(serde_json::from_slice(&body)));
+        }
+    }
+}
+
+/// Writes a message to the client.
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+/// Error returned to the client in response to a request.
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    fn into_response(self, id: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": self.code, "message": self.message },
+        })
+    }
+}
+
+/// Help file known to the server, either opened in the editor or found within the workspace.
+struct Document {
+    parser: Parser,
+
+    /// Every `*tag*` defined by the document along with where it is defined.
+    tags: Vec<(String, tree_sitter::Range)>,
+
+    /// If true, the text of the document comes from the editor rather than the file.
+    is_open: bool,
+}
+
+impl Document {
+    fn new(parser: Parser, is_open: bool) -> Self {
+        let tags = tag_ranges(&parser);
+        Self {
+            parser,
+            tags,
+            is_open,
+        }
+    }
+}
+
+/// State of the language server, holding every help file of the workspace so taglinks can be
+/// resolved across files.
+struct Server {
+    parser: tree_sitter::Parser,
+
+    /// Documents by their uri.
+    documents: BTreeMap<String, Document>,
+
+    /// Tags of every document, used to resolve taglinks when linting.
+    index: TagIndex,
+
+    /// First definition of each tag across every document, as the uri and range of the tag.
+    tags: HashMap<String, (String, tree_sitter::Range)>,
+
+    /// Notifications to send to the client once the current message is handled.
+    notifications: Vec<Value>,
+
+    is_shutdown: bool,
+}
+
+impl Server {
+    fn new() -> Result<Self, Error> {
+        Ok(Self {
+            parser: Parser::vimdoc_tree_sitter_parser()?,
+            documents: BTreeMap::new(),
+            index: TagIndex::new(),
+            tags: HashMap::new(),
+            notifications: Vec::new(),
+            is_shutdown: false,
+        })
+    }
+
+    /// Handles a request or notification from the client, returning the result of a request.
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "initialized" => Ok(Value::Null),
+            "shutdown" => {
+                self.is_shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" => self.did_open(&params).map(|_| Value::Null),
+            "textDocument/didChange" => self.did_change(&params).map(|_| Value::Null),
+            "textDocument/didClose" => self.did_close(&params).map(|_| Value::Null),
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/completion" => self.completion(&params),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unsupported method {method:?}"),
+            )),
+        }
+    }
+
+    /// Loads the help files of the workspace and returns the capabilities of the server.
+    fn initialize(&mut self, params: &Value) -> Value {
+        let mut roots: Vec<PathBuf> = params["workspaceFolders"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|folder| folder["uri"].as_str().and_then(uri_to_path))
+            .collect();
+        if roots.is_empty() {
+            roots.extend(params["rootUri"].as_str().and_then(uri_to_path));
+        }
+        let files = find_files(
+            roots,
+            &[String::from("txt")],
+            true,
+            &mut Failures::default(),
+        );
+
+        let mut failures = Failures::default();
+        for path in files {
+            if !is_help_file(&path) {
+                continue;
+            }
+            if let Some(parser) = failures.check(Parser::load_file_with(&mut self.parser, &path)) {
+                self.documents
+                    .insert(path_to_uri(&path), Document::new(parser, false));
+            }
+        }
+        self.index_tags();
+
+        json!({
+            "capabilities": {
+                // Changes are sent incrementally so documents can be parsed again incrementally
+                "textDocumentSync": { "openClose": true, "change": 2 },
+                "definitionProvider": true,
+                "hoverProvider": true,
+                "completionProvider": { "triggerCharacters": ["|"] },
+            },
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    fn did_open(&mut self, params: &Value) -> Result<(), RpcError> {
+        let uri = document_uri(params)?;
+        let text = params["textDocument"]["text"]
+            .as_str()
+            .ok_or_else(|| RpcError::invalid_params("Missing text of document"))?;
+        let parser = Parser::load_with(&mut self.parser, text.as_bytes())
+            .map_err(|x| RpcError::invalid_params(x.to_string()))?;
+        self.update(uri, Document::new(parser, true));
+        Ok(())
+    }
+
+    fn did_change(&mut self, params: &Value) -> Result<(), RpcError> {
+        let uri = document_uri(params)?;
+        let Some(document) = self.documents.get_mut(&uri) else {
+            return Err(RpcError::invalid_params(format!(
+                "Document {uri} is not open"
+            )));
+        };
+
+        // Changes with a range edit the existing tree, while changes without one replace the text
+        for change in params["contentChanges"].as_array().into_iter().flatten() {
+            let text = change["text"].as_str().unwrap_or_default();
+            let result = match change.get("range") {
+                Some(range) => {
+                    let src = document.parser.src();
+                    let start = byte_offset(src, &range["start"]);
+                    let end = byte_offset(src, &range["end"]).max(start);
+                    document
+                        .parser
+                        .edit_with(&mut self.parser, start..end, text)
+                        .map(|_| ())
+                }
+                None => Parser::load_with(&mut self.parser, text.as_bytes())
+                    .map(|parser| document.parser = parser),
+            };
+            result.map_err(|x| RpcError::invalid_params(x.to_string()))?;
+        }
+
+        let tags = tag_ranges(&document.parser);
+        let tags_changed = !same_tags(&document.tags, &tags);
+        document.tags = tags;
+        self.refresh(&uri, tags_changed);
+        Ok(())
+    }
+
+    fn did_close(&mut self, params: &Value) -> Result<(), RpcError> {
+        let uri = document_uri(params)?;
+        self.publish_diagnostics(&uri, Vec::new());
+
+        // Help files of the workspace are still needed to resolve taglinks once closed
+        let path = uri_to_path(&uri).filter(|path| path.is_file() && is_help_file(path));
+        match path.map(|path| Parser::load_file_with(&mut self.parser, path)) {
+            Some(Ok(parser)) => self.update(uri, Document::new(parser, false)),
+            _ => {
+                self.documents.remove(&uri);
+                self.index_tags();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the location of the tag linked to at the position, if any.
+    fn definition(&self, params: &Value) -> Result<Value, RpcError> {
+        let Some((_, tag)) = self.link_at(params)? else {
+            return Ok(Value::Null);
+        };
+        Ok(match self.tags.get(&tag) {
+            Some((uri, range)) => json!({ "uri": uri, "range": self.range(uri, range) }),
+            None => Value::Null,
+        })
+    }
+
+    /// Returns the section of the tag linked to at the position, if any.
+    fn hover(&self, params: &Value) -> Result<Value, RpcError> {
+        let Some((link, tag)) = self.link_at(params)? else {
+            return Ok(Value::Null);
+        };
+        let Some((uri, range)) = self.tags.get(&tag) else {
+            return Ok(Value::Null);
+        };
+        let document = &self.documents[uri];
+
+        // The section of a tag continues until the next line with a tag or a separator
+        let row = range.start_point.row;
+        let next_row = document
+            .tags
+            .iter()
+            .map(|(_, range)| range.start_point.row)
+            .find(|r| *r > row);
+        let lines: Vec<&str> = document
+            .parser
+            .src()
+            .lines()
+            .enumerate()
+            .skip(row)
+            .take_while(|(r, line)| {
+                (*r == row || !is_separator(line)) && next_row.map_or(true, |next| *r < next)
+            })
+            .take(MAX_HOVER_LINES)
+            .map(|(_, line)| line)
+            .collect();
+        let section = lines.join("\n");
+
+        let uri = document_uri(params)?;
+        Ok(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```help\n{}\n```", section.trim_end()),
+            },
+            "range": self.range(&uri, &link),
+        }))
+    }
+
+    /// Returns the tags that complete the taglink being typed at the position, if any.
+    fn completion(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = document_uri(params)?;
+        let Some(document) = self.documents.get(&uri) else {
+            return Ok(Value::Null);
+        };
+        let src = document.parser.src();
+        let offset = byte_offset(src, &params["position"]);
+        let line_start = src[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let before = &src[line_start..offset];
+
+        // An odd number of bars before the position means a taglink is being typed
+        if before.matches('|').count() % 2 == 0 {
+            return Ok(Value::Null);
+        }
+        let prefix = before.rsplit('|').next().unwrap_or_default();
+        if prefix.contains(char::is_whitespace) {
+            return Ok(Value::Null);
+        }
+
+        let mut tags: Vec<(&String, &String)> = self
+            .tags
+            .iter()
+            .filter(|(tag, _)| tag.starts_with(prefix))
+            .map(|(tag, (uri, _))| (tag, uri))
+            .collect();
+        tags.sort();
+        let is_incomplete = tags.len() > MAX_COMPLETIONS;
+        let items: Vec<Value> = tags
+            .into_iter()
+            .take(MAX_COMPLETIONS)
+            .map(|(tag, uri)| {
+                let file = uri.rsplit('/').next().map(percent_decode);
+                json!({ "label": tag, "kind": 18, "detail": file })
+            })
+            .collect();
+        Ok(json!({ "isIncomplete": is_incomplete, "items": items }))
+    }
+
+    /// Returns the range and tag of the taglink or optionlink at the position, if any.
+    fn link_at(&self, params: &Value) -> Result<Option<(tree_sitter::Range, String)>, RpcError> {
+        let uri = document_uri(params)?;
+        let Some(document) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let parser = &document.parser;
+        let offset = byte_offset(parser.src(), &params["position"]);
+
+        let mut node = parser
+            .tree()
+            .root_node()
+            .descendant_for_byte_range(offset, offset);
+        while let Some(n) = node {
+            let text = n.utf8_text(parser.src().as_bytes()).unwrap_or_default();
+            let tag = match n.node_type() {
+                Some(NodeType::Taglink) => text.trim().trim_matches('|'),
+                Some(NodeType::Optionlink) => text.trim(),
+                _ => {
+                    node = n.parent();
+                    continue;
+                }
+            };
+            return Ok(Some((n.range(), tag.to_string())));
+        }
+        Ok(None)
+    }
+
+    /// Replaces the document at `uri`.
+    fn update(&mut self, uri: String, document: Document) {
+        let tags_changed = match self.documents.get(&uri) {
+            Some(previous) => !same_tags(&previous.tags, &document.tags),
+            None => true,
+        };
+        self.documents.insert(uri.clone(), document);
+        self.refresh(&uri, tags_changed);
+    }
+
+    /// Indexes the tags of every document again if the tags of the document at `uri` changed, as
+    /// taglinks of any document may resolve differently, and otherwise only publishes the
+    /// diagnostics of the document if it is open.
+    fn refresh(&mut self, uri: &str, tags_changed: bool) {
+        if tags_changed {
+            self.index_tags();
+        } else if self.documents.get(uri).map_or(false, |x| x.is_open) {
+            self.lint(uri);
+        }
+    }
+
+    /// Indexes the tags of every document and publishes the diagnostics of open documents, whose
+    /// taglinks may now resolve differently.
+    fn index_tags(&mut self) {
+        self.index = TagIndex::new();
+        self.tags.clear();
+        for (uri, document) in self.documents.iter() {
+            self.index.insert_from_parser(&document.parser, uri);
+            for (tag, range) in document.tags.iter() {
+                self.tags
+                    .entry(tag.clone())
+                    .or_insert_with(|| (uri.clone(), *range));
+            }
+        }
+
+        let open: Vec<String> = self
+            .documents
+            .iter()
+            .filter(|(_, document)| document.is_open)
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        for uri in open {
+            self.lint(&uri);
+        }
+    }
+
+    /// Publishes the problems found within the document at `uri`.
+    fn lint(&mut self, uri: &str) {
+        let Some(document) = self.documents.get(uri) else {
+            return;
+        };
+        // Tags of the other documents are defined first, so tags that they also define are
+        // reported as duplicates
+        let mut linter = Linter::with_index(&self.index);
+        for (other, other_document) in self.documents.iter().filter(|(x, _)| *x != uri) {
+            for (tag, range) in other_document.tags.iter() {
+                linter.define_tag(tag, other, range.start_point.row);
+            }
+        }
+        linter.insert(&document.parser, uri);
+
+        let src = document.parser.src();
+        let diagnostics = linter
+            .finish()
+            .into_iter()
+            .map(|diagnostic| {
+                let position = position(src, diagnostic.point.row, diagnostic.point.column);
+                let severity = match diagnostic.rule {
+                    LintRule::ParseError => 1,
+                    _ => 2,
+                };
+                json!({
+                    "range": { "start": position, "end": position },
+                    "severity": severity,
+                    "code": diagnostic.rule.to_string(),
+                    "source": env!("CARGO_PKG_NAME"),
+                    "message": diagnostic.message,
+                })
+            })
+            .collect();
+        self.publish_diagnostics(uri, diagnostics);
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) {
+        self.notifications.push(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }));
+    }
+
+    /// Returns the `range` within the document at `uri` as an LSP range.
+    fn range(&self, uri: &str, range: &tree_sitter::Range) -> Value {
+        let src = self
+            .documents
+            .get(uri)
+            .map(|document| document.parser.src())
+            .unwrap_or_default();
+        json!({
+            "start": position(src, range.start_point.row, range.start_point.column),
+            "end": position(src, range.end_point.row, range.end_point.column),
+        })
+    }
+}
+
+/// Returns every `*tag*` of the parsed document along with where it is defined.
+fn tag_ranges(parser: &Parser) -> Vec<(String, tree_sitter::Range)> {
+    struct TagRanges(Vec<(String, tree_sitter::Range)>);
+
+    impl EventVisitor for TagRanges {
+        fn enter<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) {
+            if matches!(ctx.node_type(), Some(NodeType::Tag)) && !ctx.has_error() {
+                let tag = ctx.node_raw_text().trim().trim_matches('*');
+                self.0.push((tag.to_string(), ctx.node().range()));
+            }
+        }
+    }
+
+    let mut visitor = TagRanges(Vec::new());
+    visitor.walk_named(&mut Context::new(parser.src(), &mut parser.tree().walk()));
+    visitor.0
+}
+
+/// Returns true if the tags of two versions of a document have the same names, ignoring where they
+/// are defined.
+fn same_tags(a: &[(String, tree_sitter::Range)], b: &[(String, tree_sitter::Range)]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|((a, _), (b, _))| a == b)
+}
+
+/// Returns true if the file at `path` is within a `doc` directory, where help files live, which
+/// avoids indexing other text files of the workspace such as licenses.
+fn is_help_file(path: &Path) -> bool {
+    path.parent()
+        .and_then(Path::file_name)
+        .map_or(false, |dir| dir == "doc")
+}
+
+/// Returns the uri of the text document of the request, normalized so it matches the uri of the
+/// same file found within the workspace.
+fn document_uri(params: &Value) -> Result<String, RpcError> {
+    let uri = params["textDocument"]["uri"]
+        .as_str()
+        .ok_or_else(|| RpcError::invalid_params("Missing uri of text document"))?;
+    Ok(uri_to_path(uri).map_or_else(|| uri.to_string(), |path| path_to_uri(&path)))
+}
+
+/// Returns the byte offset within `src` of an LSP position, whose character is counted in UTF-16
+/// code units. Positions beyond the end of a line are clamped to the end of the line.
+fn byte_offset(src: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or_default() as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+
+    let Some(line_start) = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .nth(line)
+    else {
+        return src.len();
+    };
+    let mut units = 0;
+    for (i, c) in src[line_start..].char_indices() {
+        if c == '\n' || units >= character {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    src.len()
+}
+
+/// Returns the LSP position of the byte `column` of the `row` within `src`.
+fn position(src: &str, row: usize, column: usize) -> Value {
+    let line = src.lines().nth(row).unwrap_or_default();
+    let column = line
+        .char_indices()
+        .take_while(|(i, _)| *i < column)
+        .map(|(_, c)| c.len_utf16())
+        .sum::<usize>();
+    json!({ "line": row, "character": column })
+}
+
+/// Returns true if the `line` separates sections of a help file, like `====` or `----`.
+fn is_separator(line: &str) -> bool {
+    let line = line.trim_end();
+    line.len() >= 10 && (line.chars().all(|c| c == '=') || line.chars().all(|c| c == '-'))
+}
+
+/// Returns the `file` uri of the `path`.
+fn path_to_uri(path: &Path) -> String {
+    let path = std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    format!("file://{}", url_encode_path(&path.to_string_lossy()))
+}
+
+/// Returns the path of a `file` uri.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    uri.strip_prefix("file://")
+        .map(|path| PathBuf::from(percent_decode(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELP_FILE: &str = "*test.txt*\tTest
+
+==============================================================================
+INTRODUCTION\t\t\t\t\t\t\t*test-intro*
+
+Tests the language server. See |test-intro|.
+Complete |test-i
+
+ vim:tw=78:ts=8:ft=help:norl:
+";
+
+    /// Frames each of the `messages` as the client would send it.
+    fn script(messages: &[Value]) -> Vec<u8> {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        input
+    }
+
+    /// Returns a request from the client.
+    fn request(id: i64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    /// Returns every message written by the server.
+    fn responses(mut output: &[u8]) -> Vec<Value> {
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(message.unwrap());
+        }
+        messages
+    }
+
+    /// Returns the response to the request with the `id`.
+    fn response(messages: &[Value], id: i64) -> &Value {
+        messages
+            .iter()
+            .find(|message| message["id"] == id)
+            .unwrap_or_else(|| panic!("Missing response to request {id}"))
+    }
+
+    #[test]
+    fn should_answer_scripted_session() {
+        let uri = "file:///tmp/doc/test.txt";
+        let position = |line: u64, character: u64| {
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+            })
+        };
+        let input = script(&[
+            request(1, "initialize", json!({})),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": {
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": "help",
+                        "version": 1,
+                        "text": HELP_FILE,
+                    },
+                },
+            }),
+            request(2, "textDocument/definition", position(5, 35)),
+            request(3, "textDocument/hover", position(5, 35)),
+            request(4, "textDocument/completion", position(6, 16)),
+            request(5, "shutdown", Value::Null),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+
+        let mut output = Vec::new();
+        let code = run_with(input.as_slice(), &mut output).unwrap();
+        assert_eq!(code, 0);
+
+        let messages = responses(&output);
+        let capabilities = &response(&messages, 1)["result"]["capabilities"];
+        assert_eq!(capabilities["definitionProvider"], true);
+        assert_eq!(capabilities["textDocumentSync"]["change"], 2);
+
+        let diagnostics = messages
+            .iter()
+            .find(|message| message["method"] == "textDocument/publishDiagnostics")
+            .expect("Missing diagnostics of opened document");
+        assert_eq!(diagnostics["params"]["uri"], uri);
+
+        let definition = &response(&messages, 2)["result"];
+        assert_eq!(definition["uri"], uri);
+        assert_eq!(definition["range"]["start"]["line"], 3);
+
+        let hover = response(&messages, 3)["result"]["contents"]["value"]
+            .as_str()
+            .unwrap();
+        assert!(hover.contains("*test-intro*"), "{hover}");
+        assert!(!hover.contains("======"), "{hover}");
+
+        let items = response(&messages, 4)["result"]["items"]
+            .as_array()
+            .unwrap();
+        let labels: Vec<&str> = items
+            .iter()
+            .filter_map(|item| item["label"].as_str())
+            .collect();
+        assert_eq!(labels, ["test-intro"]);
+
+        assert_eq!(response(&messages, 5)["result"], Value::Null);
+    }
+
+    #[test]
+    fn should_exit_with_error_without_shutdown() {
+        let input = script(&[json!({ "jsonrpc": "2.0", "method": "exit" })]);
+        let mut output = Vec::new();
+        assert_eq!(run_with(input.as_slice(), &mut output).unwrap(), 1);
+    }
+
+    #[test]
+    fn should_skip_message_without_content_length() {
+        let mut input = b"Content-Type: application/vscode-jsonrpc\r\n\r\n".to_vec();
+        input.extend(script(&[
+            request(1, "shutdown", Value::Null),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]));
+
+        let mut output = Vec::new();
+        assert_eq!(run_with(input.as_slice(), &mut output).unwrap(), 0);
+        assert_eq!(response(&responses(&output), 1)["result"], Value::Null);
+    }
+}
@@ -1,17 +1,60 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::ffi::OsStr;
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
-mod parser;
-mod utils;
+use vimdoc2html::*;
 
-use parser::*;
+mod lsp;
+mod serve;
+
+/// Name of the stylesheet shared by standalone documents within the same directory.
+const STYLESHEET: &str = "vimdoc.css";
+
+/// Name of the file within the output directory that records every output written into it.
+const MANIFEST: &str = ".vimdoc2html-manifest";
+
+/// Time between checking watched files for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
 
 /// Convert vimdoc into html.
 #[derive(clap::Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Generate Vim-compatible tags files for directories of vimdoc, like `:helptags`.
+    Tags(TagsArgs),
+
+    /// Format vimdoc into its canonical form, aligning tags, separators, and code blocks.
+    Fmt(FmtArgs),
+
+    /// Check vimdoc for problems such as broken taglinks, duplicate tags, and long lines.
+    #[command(visible_alias = "check")]
+    Lint(LintArgs),
+
+    /// Convert vimdoc into a site served locally, converting again and reloading open pages when
+    /// the vimdoc changes.
+    Serve(ServeArgs),
+
+    /// Run a language server over stdin and stdout, providing diagnostics, go to definition,
+    /// hover, and completion of taglinks across the help files of the workspace.
+    Lsp,
+}
+
+#[derive(clap::Args, Debug)]
+struct ConvertArgs {
     /// File extensions to look for when converting a directory of vimdoc.
     #[arg(short, long, default_values_t = vec![String::from("txt")])]
     extensions: Vec<String>,
@@ -20,10 +63,93 @@ struct Args {
     #[arg(short, long)]
     recursive: bool,
 
+    /// Tags files of external documentation used to link taglinks that are not found locally,
+    /// each in the form `<TAGS_FILE>=<URL_TEMPLATE>`. Within the template, `{file}` is replaced
+    /// with the help file name without extension and `{tag}` with the tag, e.g.
+    /// `$VIMRUNTIME/doc/tags=https://neovim.io/doc/user/{file}.html#{tag}`.
+    #[arg(long = "external-tags", value_name = "TAGS_FILE=URL_TEMPLATE")]
+    external_tags: Vec<ExternalTags>,
+
+    /// If specified, will write out only an HTML fragment instead of a standalone document.
+    #[arg(long)]
+    fragment: bool,
+
+    /// Path to a template used to produce standalone documents. The placeholders `{{title}}`,
+    /// `{{head}}`, `{{toc}}`, `{{body}}`, `{{prev}}`, and `{{next}}` are replaced when rendered.
+    #[arg(long, value_name = "FILE")]
+    template: Option<PathBuf>,
+
+    /// Custom HTML to include within the `<head>` of standalone documents.
+    #[arg(long, value_name = "HTML", default_value_t)]
+    head: String,
+
+    /// Theme used to style standalone documents: default, light, dark, or terminal.
+    #[arg(long, default_value_t)]
+    theme: HtmlTheme,
+
+    /// If specified, will inline the stylesheet into each standalone document instead of writing
+    /// a shared stylesheet next to the output. Always inlined when reading from stdin.
+    #[arg(long)]
+    inline_css: bool,
+
+    /// If specified, will not include a table of contents at the top of each page.
+    #[arg(long)]
+    no_toc: bool,
+
+    /// If specified, will link each key of a keycode to the tag of its notation like `<CR>`, or to
+    /// `key-notation` when the key has no tag of its own.
+    #[arg(long)]
+    link_keycodes: bool,
+
+    /// If specified, will also write the table of contents of each file as JSON next to its
+    /// output. When reading from stdin, the JSON is printed instead of HTML.
+    #[arg(long)]
+    toc_json: bool,
+
+    /// Format to convert vimdoc into.
+    #[arg(short, long, value_enum, default_value_t = Format::Html)]
+    format: Format,
+
+    /// If specified with the json format, will print the tree of each file as a single line of
+    /// JSON to stdout instead of writing files.
+    #[arg(long)]
+    ndjson: bool,
+
+    /// If specified, will validate links, urls, and parse errors of the vimdoc like Neovim's
+    /// `gen_help_html.lua`, printing a JSON report to stdout instead of converting and exiting with
+    /// an error if any problems are found. Help files are keyed by their path relative to the input
+    /// they were found in.
+    #[arg(long)]
+    validate: bool,
+
     /// If specified, will write out a debug string instead of HTML.
     #[arg(long)]
     debug_output: bool,
 
+    /// Directory to write output into, mirroring the directory layout of the inputs. By default,
+    /// output is written next to each input file.
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    out_dir: Option<PathBuf>,
+
+    /// File to write the output of a single input into, or `-` to print it to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// If specified, will remove files within the output directory that a previous conversion
+    /// wrote but whose source vimdoc no longer exists, as recorded in the directory's manifest.
+    #[arg(long, requires = "out_dir")]
+    clean: bool,
+
+    /// Number of files to parse and convert concurrently, or 0 to use every available CPU. Output
+    /// and log messages are the same regardless of the number of jobs.
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// If specified, will keep running after converting and convert again the files that change,
+    /// along with every other page when the tags of a file change.
+    #[arg(short, long, requires = "paths", conflicts_with_all = ["validate", "ndjson"])]
+    watch: bool,
+
     /// If specified, will not print anything to stdout.
     #[arg(short, long)]
     quiet: bool,
@@ -33,67 +159,962 @@ struct Args {
     paths: Vec<PathBuf>,
 }
 
+/// Formats that vimdoc can be converted into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    Html,
+    /// Tree of named nodes with source positions
+    Json,
+    /// CommonMark with GitHub extensions
+    Markdown,
+}
+
+impl Format {
+    /// Returns the file extension used for output of this format.
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Json => "json",
+            Self::Markdown => "md",
+        }
+    }
+}
+
+/// Vimdoc file that has been parsed and is waiting to be converted.
+struct ParsedFile {
+    path: PathBuf,
+    outfile: PathBuf,
+    page: String,
+    title: String,
+    parser: Parser,
+}
+
+/// Tags file of external documentation paired with the url template used to link to it.
+#[derive(Clone, Debug)]
+struct ExternalTags {
+    path: PathBuf,
+    url_template: String,
+}
+
+impl FromStr for ExternalTags {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((path, url_template)) if !path.is_empty() && !url_template.is_empty() => {
+                Ok(Self {
+                    path: PathBuf::from(path),
+                    url_template: url_template.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Expected <TAGS_FILE>=<URL_TEMPLATE>, but got {s:?}"
+            )),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct FmtArgs {
+    /// If specified, will not write any files and instead exit with an error if any file is not
+    /// already formatted.
+    #[arg(long)]
+    check: bool,
+
+    /// File extensions to look for when formatting a directory of vimdoc.
+    #[arg(short, long, default_values_t = vec![String::from("txt")])]
+    extensions: Vec<String>,
+
+    /// If specified, will recursively look through directories for vimdoc files.
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// If specified, will not print anything to stdout.
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Paths to format in place. If no paths are provided, will read vimdoc from stdin until EOF
+    /// detected and then print out the formatted vimdoc.
+    paths: Vec<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct LintArgs {
+    /// File extensions to look for when checking a directory of vimdoc.
+    #[arg(short, long, default_values_t = vec![String::from("txt")])]
+    extensions: Vec<String>,
+
+    /// If specified, will recursively look through directories for vimdoc files.
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Tags files of external documentation that taglinks can resolve to, each in the form
+    /// `<TAGS_FILE>=<URL_TEMPLATE>` like when converting.
+    #[arg(long = "external-tags", value_name = "TAGS_FILE=URL_TEMPLATE")]
+    external_tags: Vec<ExternalTags>,
+
+    /// Format to print diagnostics in.
+    #[arg(short, long, value_enum, default_value_t = LintFormat::Text)]
+    format: LintFormat,
+
+    /// Paths to check. Taglinks can resolve to tags within any of the checked files.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+/// Formats that diagnostics can be printed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum LintFormat {
+    /// One `file:line:col: rule: message` per line
+    Text,
+    /// Array of diagnostics
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on, or 0 to use any available port.
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct TagsArgs {
+    /// If specified, will not write any tags files and instead exit with an error if any existing
+    /// tags file is out of date.
+    #[arg(long)]
+    check: bool,
+
+    /// If specified, will not print anything to stdout.
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Directories containing vimdoc. Like `:helptags`, `*.txt` files produce `tags` and
+    /// translated `*.??x` files produce `tags-??`.
+    #[arg(required = true)]
+    dirs: Vec<PathBuf>,
+}
+
 fn main() {
-    let Args {
+    let Args { command, convert } = <Args as clap::Parser>::parse();
+    let result = match command {
+        Some(Command::Tags(args)) => generate_tags(args),
+        Some(Command::Fmt(args)) => format_vimdoc(args),
+        Some(Command::Lint(args)) => lint_vimdoc(args),
+        Some(Command::Serve(args)) => serve_vimdoc(args),
+        Some(Command::Lsp) => lsp::run(),
+        None => convert_vimdoc(convert, || {}),
+    };
+
+    if let Err(x) = result {
+        eprintln!("{x}");
+        std::process::exit(1);
+    }
+}
+
+/// Result of a command, failing only when the command cannot proceed at all. Errors of individual
+/// files are instead collected by [`Failures`].
+type CommandResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Errors of individual files, reported once every file has been processed so that one bad file
+/// does not stop the rest.
+#[derive(Default)]
+struct Failures(Vec<Error>);
+
+impl Failures {
+    /// Returns the value of the `result`, otherwise prints and records its error.
+    fn check<T>(&mut self, result: Result<T, Error>) -> Option<T> {
+        match result {
+            Ok(x) => Some(x),
+            Err(x) => {
+                eprintln!("{x}");
+                self.0.push(x);
+                None
+            }
+        }
+    }
+
+    /// Prints a summary and exits with an error if any of the `total` files failed to `action`.
+    fn exit_if_any(self, action: &str, total: usize) {
+        if !self.0.is_empty() {
+            eprintln!("Failed to {action} {} of {total} file(s)", self.0.len());
+            std::process::exit(1);
+        }
+    }
+}
+
+fn convert_vimdoc(
+    ConvertArgs {
         extensions,
         recursive,
+        external_tags,
+        fragment,
+        template,
+        head,
+        theme,
+        inline_css,
+        no_toc,
+        link_keycodes,
+        toc_json,
+        format,
+        ndjson,
+        validate,
         debug_output,
+        out_dir,
+        output,
+        clean,
+        jobs,
+        watch,
         quiet,
         paths,
-    } = <Args as clap::Parser>::parse();
+    }: ConvertArgs,
+    mut on_watch: impl FnMut(),
+) -> CommandResult {
     let should_read_stdin = paths.is_empty();
+    let should_write_stdout = output.as_deref() == Some(Path::new("-"));
+    let quiet = quiet || should_write_stdout;
+
+    // Index the tags of external documentation first, which are only used as a fallback when
+    // a tag is not found within the files being converted
+    let mut index = load_external_tags(external_tags)?;
+
+    // Standalone documents share the same template, stylesheet, and head, differing in title and
+    // pager links
+    let inline_css = inline_css || should_read_stdin || should_write_stdout;
+    let head = if inline_css {
+        format!("<style>\n{}</style>\n{head}", theme.css())
+    } else {
+        format!("<link rel=\"stylesheet\" href=\"{STYLESHEET}\">\n{head}")
+    };
+    let template = match template {
+        Some(path) => HtmlTemplate::new(
+            std::fs::read_to_string(&path).map_err(|x| Error::from(x).with_path(path))?,
+        ),
+        None => HtmlTemplate::default(),
+    };
+    let document = |title: Option<String>, prev: Option<PageLink>, next: Option<PageLink>| {
+        if fragment {
+            None
+        } else {
+            Some(HtmlDocumentOpt {
+                template: template.clone(),
+                title,
+                head: head.clone(),
+                prev,
+                next,
+            })
+        }
+    };
 
     // If we are reading stdin, then we block until we get all input, feed it into our parser, and
     // then print out the results
     if should_read_stdin {
-        let parser = Parser::load_vimdoc(std::io::stdin()).expect("Failed to load parser");
+        let parser = Parser::load_vimdoc(std::io::stdin())?;
+        if validate {
+            index.insert_from_parser(&parser, "");
+            let mut report = ValidationReport::new();
+            report.insert(&parser, "-", &index);
+            print_validation_report(&report, &["-"])?;
+            return Ok(());
+        }
+
         let out: String = if debug_output {
-            parser
-                .parse::<DebugString>()
-                .expect("Failed to parse into debug")
-                .into()
+            parser.parse::<DebugString>()?.into()
+        } else if toc_json {
+            toc_to_json(&parser)?
+        } else if format == Format::Json {
+            let ast = parser.parse::<JsonAst>()?;
+            if ndjson {
+                serde_json::to_string(&ast)?
+            } else {
+                ast.to_string_pretty()?
+            }
+        } else if format == Format::Markdown {
+            MarkdownString::from_parser_with_opt(
+                &parser,
+                MarkdownTranslatorOpt {
+                    tags: Some(&index),
+                    ..Default::default()
+                },
+            )?
+            .into()
         } else {
-            parser
-                .parse::<HtmlString>()
-                .expect("Failed to parse into HTML")
-                .into()
+            HtmlString::from_parser_with_opt(
+                &parser,
+                HtmlTranslatorOpt {
+                    tags: Some(&index),
+                    toc: !no_toc,
+                    link_keycodes,
+                    document: document(None, None, None),
+                    ..Default::default()
+                },
+            )?
+            .into()
         };
-        println!("{out}");
-        return;
+        match output {
+            Some(output) if !should_write_stdout => write_file(&output, out)?,
+            _ => println!("{out}"),
+        }
+        return Ok(());
     }
 
-    // Otherwise, we read in all of the paths and process sequentially.
-    //
-    // * For a file, we read it in as a byte
-    let mut paths: VecDeque<PathBuf> = paths.into();
-    while let Some(path) = paths.pop_front() {
-        if path.is_file() {
-            let outfile = path.with_extension("html");
-            if !quiet {
+    // Inputs are the roots of the directory layout mirrored into the output directory, where
+    // files are relative to their parent directory
+    let roots: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| match path.parent() {
+            _ if path.is_dir() => path.to_path_buf(),
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        })
+        .collect();
+
+    // Otherwise, we gather all of the files to convert, walking directories as needed
+    let mut failures = Failures::default();
+    let files = find_files(paths.clone(), &extensions, recursive, &mut failures);
+    let total = files.len() + failures.0.len();
+    if output.is_some() && files.len() != 1 {
+        return Err(format!(
+            "--output requires a single input file, but found {}",
+            files.len()
+        )
+        .into());
+    }
+
+    // First pass parses every file, reusing a tree-sitter parser per job, and determines where the
+    // output of each file is written
+    let jobs = match jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let parse_files = |files: &[PathBuf]| -> Vec<Result<ParsedFile, Error>> {
+        parallel_map(
+            files,
+            jobs,
+            || Parser::vimdoc_tree_sitter_parser().ok(),
+            |parser, _, path| {
+                let parser = match parser {
+                    Some(parser) => Parser::load_file_with(parser, path)?,
+                    None => Parser::load_vimdoc_file(path)?,
+                };
+                let (outfile, page) = match (&output, &out_dir) {
+                    (Some(output), _) => (output.to_path_buf(), page_name(output)),
+                    (None, Some(out_dir)) => {
+                        let relative =
+                            relative_to_roots(path, &roots).with_extension(format.extension());
+                        (out_dir.join(&relative), page_name(&relative))
+                    }
+                    (None, None) => {
+                        let outfile = path.with_extension(format.extension());
+                        let page = page_name(&outfile);
+                        (outfile, page)
+                    }
+                };
+                let title = match parser.title() {
+                    Some(tag) => tag.to_string(),
+                    None => path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                };
+                Ok(ParsedFile {
+                    path: path.to_path_buf(),
+                    outfile,
+                    page,
+                    title,
+                    parser,
+                })
+            },
+        )
+    };
+    let mut parsed: Vec<ParsedFile> = parse_files(&files)
+        .into_iter()
+        .filter_map(|file| failures.check(file))
+        .collect();
+
+    // Then the tags of each file are indexed in order so taglinks can refer to other pages
+    let external_index = index;
+    let build_index = |parsed: &[ParsedFile]| {
+        let mut index = external_index.clone();
+        for file in parsed {
+            index.insert_from_parser(&file.parser, &file.page);
+        }
+        index
+    };
+    let mut index = build_index(&parsed);
+
+    if validate {
+        let mut report = ValidationReport::new();
+        let mut helpfiles = Vec::new();
+        for ParsedFile { path, parser, .. } in parsed.iter() {
+            // Help files are keyed by their path relative to the input they were found in, unless
+            // another input already has a help file at the same relative path
+            let helpfile = match page_name(&relative_to_roots(path, &roots)) {
+                helpfile if helpfiles.contains(&helpfile) => page_name(path),
+                helpfile => helpfile,
+            };
+            report.insert(parser, &helpfile, &index);
+            helpfiles.push(helpfile);
+        }
+        print_validation_report(
+            &report,
+            &helpfiles.iter().map(String::as_str).collect::<Vec<_>>(),
+        )?;
+        failures.exit_if_any("validate", total);
+        return Ok(());
+    }
+
+    let is_ndjson = format == Format::Json && ndjson;
+
+    // Second pass converts the selected files concurrently, resolving taglinks using the index and
+    // linking each page to the pages before and after it. Output that is printed rather than
+    // written to a file is returned to be printed in order, and the directories of written files
+    // are returned so they can share a stylesheet
+    let convert_files = |parsed: &[ParsedFile],
+                         index: &TagIndex,
+                         selected: &[usize],
+                         failures: &mut Failures| {
+        let converted = parallel_map(
+            selected,
+            jobs,
+            || (),
+            |_, _, &i| {
+                let ParsedFile {
+                    path,
+                    outfile,
+                    page,
+                    title,
+                    parser,
+                } = &parsed[i];
+
+                // Output directories mirror the inputs, so they may not exist yet
+                let should_print = should_write_stdout || is_ndjson;
+                if let Some(outdir) = outfile.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    if !should_print {
+                        std::fs::create_dir_all(outdir)
+                            .map_err(|x| Error::from(x).with_path(outdir))?;
+                    }
+                }
+
+                if toc_json && !should_print {
+                    let tocfile = outfile.with_extension("toc.json");
+                    toc_to_json(parser)
+                        .map_err(|x| x.with_path(path))
+                        .and_then(|toc| write_file(&tocfile, toc))?;
+                }
+
+                let out: Result<String, Error> = if is_ndjson {
+                    parser.parse::<JsonAst>().and_then(|ast| {
+                        let ast = serde_json::to_value(ast)?;
+                        let path = path.to_string_lossy();
+                        Ok(serde_json::json!({ "path": path, "ast": ast }).to_string())
+                    })
+                } else if debug_output {
+                    parser.parse::<DebugString>().map(String::from)
+                } else if format == Format::Json {
+                    parser
+                        .parse::<JsonAst>()
+                        .and_then(|ast| ast.to_string_pretty())
+                } else if format == Format::Markdown {
+                    MarkdownString::from_parser_with_opt(
+                        parser,
+                        MarkdownTranslatorOpt {
+                            tags: Some(index),
+                            page: Some(page.clone()),
+                        },
+                    )
+                    .map(String::from)
+                } else {
+                    let link = |other: &ParsedFile| PageLink::new(page, &other.page, &other.title);
+                    let prev = i.checked_sub(1).and_then(|i| parsed.get(i)).map(link);
+                    let next = parsed.get(i + 1).map(link);
+                    let opt = HtmlTranslatorOpt {
+                        tags: Some(index),
+                        page: Some(page.clone()),
+                        toc: !no_toc,
+                        link_keycodes,
+                        document: document(Some(title.clone()), prev, next),
+                        ..Default::default()
+                    };
+
+                    // HTML is written directly into the file as it is converted
+                    if !should_print {
+                        return write_html(outfile, parser, opt)
+                            .map(|_| None)
+                            .map_err(|x| match x.path() {
+                                Some(_) => x,
+                                None => x.with_path(path),
+                            });
+                    }
+                    HtmlString::from_parser_with_opt(parser, opt).map(String::from)
+                };
+
+                let out = out.map_err(|x| x.with_path(path))?;
+                if should_print {
+                    Ok(Some(out))
+                } else {
+                    write_file(outfile, out).map(|_| None)
+                }
+            },
+        );
+
+        // Results are reported in the order of the files, so logs are the same regardless of jobs
+        let mut outdirs = BTreeSet::new();
+        for (&i, result) in selected.iter().zip(converted) {
+            let ParsedFile { path, outfile, .. } = &parsed[i];
+            if !quiet && !is_ndjson {
                 println!("Converting {path:?} into {outfile:?}");
             }
 
-            let parser = Parser::load_vimdoc(File::open(path).expect("Failed to open file"))
-                .expect("Failed to load parser");
-            let out: String = if debug_output {
-                parser
-                    .parse::<DebugString>()
-                    .expect("Failed to parse into debug")
-                    .into()
+            match failures.check(result) {
+                Some(Some(out)) => println!("{out}"),
+                Some(None) => {
+                    if let Some(outdir) = outfile.parent() {
+                        outdirs.insert(outdir.to_path_buf());
+                    }
+                }
+                None => {}
+            }
+        }
+        outdirs
+    };
+
+    // Documents that do not inline their stylesheet link to one shared within their directory
+    let has_stylesheets = format == Format::Html && !debug_output && !fragment && !inline_css;
+    let write_stylesheets = |outdirs: BTreeSet<PathBuf>| -> Result<(), Error> {
+        if has_stylesheets {
+            let css = theme.css();
+            for outdir in outdirs {
+                write_file(&outdir.join(STYLESHEET), &css)?;
+            }
+        }
+        Ok(())
+    };
+
+    let outdirs = convert_files(
+        &parsed,
+        &index,
+        &(0..parsed.len()).collect::<Vec<_>>(),
+        &mut failures,
+    );
+
+    // Outputs within the output directory are recorded in a manifest, so cleaning only removes
+    // files that were written by a previous conversion and never files placed there by hand
+    let update_manifest = |files: &[PathBuf], failures: &mut Failures| {
+        let Some(out_dir) = out_dir.as_deref().filter(|_| !is_ndjson) else {
+            return;
+        };
+        let mut outputs: BTreeSet<PathBuf> = files
+            .iter()
+            .flat_map(|path| {
+                let outfile = relative_to_roots(path, &roots).with_extension(format.extension());
+                let tocfile = toc_json.then(|| outfile.with_extension("toc.json"));
+                std::iter::once(outfile).chain(tocfile)
+            })
+            .collect();
+        if has_stylesheets {
+            let stylesheets: Vec<PathBuf> = outputs
+                .iter()
+                .map(|output| output.with_file_name(STYLESHEET))
+                .collect();
+            outputs.extend(stylesheets);
+        }
+
+        for relative in read_manifest(out_dir) {
+            let path = out_dir.join(&relative);
+            if outputs.contains(&relative) || !path.exists() {
+                continue;
+            }
+            if clean {
+                if !quiet {
+                    println!("Removing stale {path:?}");
+                }
+                let removed =
+                    std::fs::remove_file(&path).map_err(|x| Error::from(x).with_path(&path));
+                if failures.check(removed).is_some() {
+                    continue;
+                }
+            }
+
+            // Stale outputs that are kept remain recorded so a later run can still clean them
+            outputs.insert(relative);
+        }
+        failures.check(write_manifest(out_dir, &outputs));
+    };
+
+    update_manifest(&files, &mut failures);
+    write_stylesheets(outdirs)?;
+
+    if !watch {
+        failures.exit_if_any("convert", total);
+        return Ok(());
+    }
+
+    // Watching polls the modification times of the inputs, converting again only the files that
+    // changed. Pages link to each other by their tags and their order, so every page is converted
+    // again when the tags of a file change or files are added or removed
+    if !quiet {
+        println!("Watching {} file(s) for changes", files.len());
+    }
+    let mut mtimes = modified_times(&files);
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+
+        // Inputs may be removed while watching, which is not an error until they return
+        let mut failures = Failures::default();
+        let existing = paths.iter().filter(|path| path.exists()).cloned().collect();
+        let files = find_files(existing, &extensions, recursive, &mut failures);
+        let latest = modified_times(&files);
+        if latest == mtimes {
+            continue;
+        }
+
+        let start = Instant::now();
+        let changed: Vec<PathBuf> = files
+            .iter()
+            .filter(|path| mtimes.get(*path) != latest.get(*path))
+            .cloned()
+            .collect();
+        let previous_paths: Vec<PathBuf> = parsed.iter().map(|file| file.path.clone()).collect();
+        let mut previous: BTreeMap<PathBuf, ParsedFile> = parsed
+            .drain(..)
+            .map(|file| (file.path.clone(), file))
+            .collect();
+
+        let mut updated = BTreeMap::new();
+        let mut tags_changed = false;
+        for file in parse_files(&changed) {
+            let Some(file) = failures.check(file) else {
+                continue;
+            };
+            let tags = collect_tags(&file.parser);
+            tags_changed |= previous.get(&file.path).map(|x| collect_tags(&x.parser)) != Some(tags);
+            updated.insert(file.path.clone(), file);
+        }
+
+        // Files that failed to parse keep their last good parse until they parse again
+        let reparsed: Vec<PathBuf> = updated.keys().cloned().collect();
+        parsed = files
+            .iter()
+            .filter_map(|path| updated.remove(path).or_else(|| previous.remove(path)))
+            .collect();
+        let layout_changed = parsed
+            .iter()
+            .map(|file| &file.path)
+            .ne(previous_paths.iter());
+        for file in previous.into_values() {
+            if latest.contains_key(&file.path) {
+                continue;
+            }
+            if !quiet {
+                println!("Removed {:?}", file.path);
+            }
+        }
+
+        let selected: Vec<usize> = if tags_changed || layout_changed {
+            index = build_index(&parsed);
+            (0..parsed.len()).collect()
+        } else {
+            (0..parsed.len())
+                .filter(|&i| reparsed.contains(&parsed[i].path))
+                .collect()
+        };
+
+        // Diagnostics are limited to the changed files to keep the output focused on the edit,
+        // while the tags of the other files are still defined so duplicates of them are reported
+        let mut linter = Linter::with_index(&index);
+        for file in parsed.iter().filter(|file| !reparsed.contains(&file.path)) {
+            linter.define_tags(&file.parser, &file.path.to_string_lossy());
+        }
+        for file in parsed.iter().filter(|file| reparsed.contains(&file.path)) {
+            linter.insert(&file.parser, &file.path.to_string_lossy());
+        }
+        for diagnostic in linter.finish() {
+            eprintln!("{diagnostic}");
+        }
+
+        let outdirs = convert_files(&parsed, &index, &selected, &mut failures);
+        update_manifest(&files, &mut failures);
+        failures.check(write_stylesheets(outdirs));
+        on_watch();
+        if !quiet {
+            println!(
+                "Converted {} file(s) in {:.2?}{}",
+                selected.len(),
+                start.elapsed(),
+                if tags_changed {
+                    " after tags changed"
+                } else {
+                    ""
+                }
+            );
+        }
+        if !failures.0.is_empty() {
+            eprintln!("Failed to convert {} file(s)", failures.0.len());
+        }
+        mtimes = latest;
+    }
+}
+
+fn serve_vimdoc(
+    ServeArgs {
+        host,
+        port,
+        mut convert,
+    }: ServeArgs,
+) -> CommandResult {
+    let unsupported = [
+        ("--validate", convert.validate),
+        ("--ndjson", convert.ndjson),
+        ("--output", convert.output.is_some()),
+        ("--fragment", convert.fragment),
+        ("--debug-output", convert.debug_output),
+        ("--format", convert.format != Format::Html),
+    ];
+    if let Some((arg, _)) = unsupported.iter().find(|(_, is_set)| *is_set) {
+        return Err(format!("{arg} cannot be used when serving").into());
+    }
+    if convert.paths.is_empty() {
+        return Err("Serving requires paths to convert".into());
+    }
+
+    // Serving runs until the process is killed, so a site written somewhere temporary would never
+    // be cleaned up
+    let Some(root) = convert.out_dir.clone() else {
+        return Err("Serving requires --out-dir for the converted site".into());
+    };
+    std::fs::create_dir_all(&root).map_err(|x| Error::from(x).with_path(&root))?;
+    convert.watch = true;
+    convert.head = format!("{}\n{}", serve::live_reload_script(), convert.head);
+
+    let listener = std::net::TcpListener::bind((host.as_str(), port))?;
+    println!("Serving {root:?} at http://{}/", listener.local_addr()?);
+
+    let live_reload = serve::LiveReload::default();
+    std::thread::spawn({
+        let live_reload = live_reload.clone();
+        move || serve::serve(listener, root, live_reload)
+    });
+    convert_vimdoc(convert, || live_reload.reload())
+}
+
+fn generate_tags(TagsArgs { check, quiet, dirs }: TagsArgs) -> CommandResult {
+    let mut failures = Failures::default();
+    let mut total = 0;
+    let mut is_stale = false;
+
+    for dir in dirs {
+        let entries = std::fs::read_dir(&dir)
+            .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+            .map_err(|x| Error::from(x).with_path(&dir));
+        let Some(entries) = failures.check(entries) else {
+            continue;
+        };
+
+        // Group the help files in the directory by the tags file they belong to
+        let mut tags_files: BTreeMap<String, TagsFile> = BTreeMap::new();
+        for entry in entries {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let ext = path.extension().and_then(OsStr::to_str).unwrap_or("");
+            let name = if ext == "txt" {
+                String::from("tags")
+            } else if ext.len() == 3 && ext.is_ascii() && ext.ends_with('x') {
+                format!("tags-{}", &ext[..2])
             } else {
-                parser
-                    .parse::<HtmlString>()
-                    .expect("Failed to parse into HTML")
-                    .into()
+                continue;
             };
-            std::fs::write(outfile, out).expect("Failed to write output");
+
+            total += 1;
+            let file = entry.file_name().to_string_lossy().to_string();
+            if let Some(parser) = failures.check(Parser::load_vimdoc_file(&path)) {
+                tags_files
+                    .entry(name)
+                    .or_default()
+                    .insert_from_parser(&parser, &file);
+            }
+        }
+
+        for (name, mut tags_file) in tags_files {
+            tags_file.sort();
+            for entry in tags_file.duplicates() {
+                eprintln!("Duplicate tag {:?} in {}", entry.tag, entry.file);
+            }
+
+            let path = dir.join(name);
+            let out = tags_file.to_string();
+            if check {
+                if std::fs::read_to_string(&path).ok().as_deref() != Some(out.as_str()) {
+                    eprintln!("{path:?} is out of date");
+                    is_stale = true;
+                }
+            } else {
+                if !quiet {
+                    println!("Writing {} tags into {path:?}", tags_file.entries.len());
+                }
+                failures.check(write_file(&path, out));
+            }
+        }
+    }
+
+    failures.exit_if_any("index", total);
+    if is_stale {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn format_vimdoc(
+    FmtArgs {
+        check,
+        extensions,
+        recursive,
+        quiet,
+        paths,
+    }: FmtArgs,
+) -> CommandResult {
+    if paths.is_empty() {
+        let out = Parser::load_vimdoc(std::io::stdin())?.parse::<VimdocString>()?;
+        print!("{}", out.as_str());
+        return Ok(());
+    }
+
+    let mut failures = Failures::default();
+    let files = find_files(paths, &extensions, recursive, &mut failures);
+    let total = files.len() + failures.0.len();
+
+    let mut is_unformatted = false;
+    for path in files {
+        let result = std::fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|src| {
+                let out = Parser::load_vimdoc(src.as_bytes())?.parse::<VimdocString>()?;
+                Ok((src, out))
+            })
+            .map_err(|x| x.with_path(&path));
+        let Some((src, out)) = failures.check(result) else {
+            continue;
+        };
+        if out.as_str() == src {
+            continue;
+        }
+
+        if check {
+            eprintln!("{path:?} is not formatted");
+            is_unformatted = true;
+        } else {
+            if !quiet {
+                println!("Formatting {path:?}");
+            }
+            failures.check(write_file(&path, out.as_str()));
+        }
+    }
+
+    failures.exit_if_any("format", total);
+    if is_unformatted {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn lint_vimdoc(
+    LintArgs {
+        extensions,
+        recursive,
+        external_tags,
+        format,
+        paths,
+    }: LintArgs,
+) -> CommandResult {
+    let index = load_external_tags(external_tags)?;
+    let mut failures = Failures::default();
+    let files = find_files(paths, &extensions, recursive, &mut failures);
+    let total = files.len() + failures.0.len();
+
+    let mut linter = Linter::with_index(&index);
+    for path in files {
+        if let Some(parser) = failures.check(Parser::load_vimdoc_file(&path)) {
+            linter.insert(&parser, &path.to_string_lossy());
+        }
+    }
+
+    let diagnostics = linter.finish();
+    match format {
+        LintFormat::Text => {
+            for diagnostic in diagnostics.iter() {
+                println!("{diagnostic}");
+            }
+            eprintln!("{} warning(s)", diagnostics.len());
+        }
+        LintFormat::Json => println!("{}", serde_json::to_string_pretty(&diagnostics)?),
+    }
+
+    failures.exit_if_any("check", total);
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Returns an index of the tags of external documentation, where each tags file is paired with
+/// the url template used to link to it.
+fn load_external_tags(external_tags: Vec<ExternalTags>) -> Result<TagIndex, Error> {
+    let mut index = TagIndex::new();
+    for ExternalTags { path, url_template } in external_tags {
+        let tags_file: TagsFile = std::fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|tags| tags.parse())
+            .map_err(|x| x.with_path(&path))?;
+        index.insert_from_tags_file(&tags_file, &url_template);
+    }
+    Ok(index)
+}
+
+/// Returns the files at `paths`, walking directories for files with one of the `extensions` and
+/// descending into nested directories when `recursive`. Directories that cannot be read are
+/// recorded as `failures` and skipped.
+fn find_files(
+    paths: Vec<PathBuf>,
+    extensions: &[String],
+    recursive: bool,
+    failures: &mut Failures,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut paths: VecDeque<PathBuf> = paths.into();
+    while let Some(path) = paths.pop_front() {
+        if path.is_file() {
+            files.push(path);
         } else if path.is_dir() {
-            for entry in std::fs::read_dir(path).expect("Failed to read directory") {
-                let entry = entry.expect("Failed to read directory entry");
-                let file_type = entry
-                    .file_type()
-                    .expect("Failed to read directory entry file type");
+            let entries = std::fs::read_dir(&path)
+                .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+                .map_err(|x| Error::from(x).with_path(&path));
+            let Some(mut entries) = failures.check(entries) else {
+                continue;
+            };
+
+            // Sort the entries so files are always converted and linked in the same order
+            entries.sort_by_key(|entry| entry.path());
+
+            for entry in entries {
                 let path = entry.path();
+                let Some(file_type) = failures.check(
+                    entry
+                        .file_type()
+                        .map_err(|x| Error::from(x).with_path(&path)),
+                ) else {
+                    continue;
+                };
                 let ext = path.extension().unwrap_or_else(|| OsStr::new(""));
 
                 // Queue up the inner path if it is a file with a matching extension or
@@ -104,6 +1125,183 @@ fn main() {
                     paths.push_back(path);
                 }
             }
+        } else {
+            failures.check::<()>(Err(Error::from(std::io::Error::from(
+                std::io::ErrorKind::NotFound,
+            ))
+            .with_path(&path)));
+        }
+    }
+
+    files
+}
+
+/// Maps the `items` using `f` across `jobs` threads, returning the results in the order of the
+/// items. Each thread creates its own state using `init`, which is reused for every item mapped by
+/// the thread.
+fn parallel_map<T, S, R>(
+    items: &[T],
+    jobs: usize,
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, usize, &T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        let mut state = init();
+        return items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| f(&mut state, i, item))
+            .collect();
+    }
+
+    // Threads take the next unmapped item until none remain, so uneven items balance out
+    let next = AtomicUsize::new(0);
+    let (init, f, next) = (&init, &f, &next);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..jobs)
+            .map(|_| {
+                s.spawn(move || {
+                    let mut state = init();
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break;
+                        };
+                        results.push((i, f(&mut state, i, item)));
+                    }
+                    results
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|x| std::panic::resume_unwind(x))
+            })
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Returns the modification time of each of the `files`, or none if it cannot be read.
+fn modified_times(files: &[PathBuf]) -> BTreeMap<PathBuf, Option<SystemTime>> {
+    files
+        .iter()
+        .map(|path| {
+            let mtime = std::fs::metadata(path).and_then(|x| x.modified()).ok();
+            (path.clone(), mtime)
+        })
+        .collect()
+}
+
+/// Returns the `path` relative to the first of the `roots` that contains it, otherwise its file
+/// name.
+fn relative_to_roots(path: &Path, roots: &[PathBuf]) -> PathBuf {
+    roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .or_else(|| path.file_name().map(Path::new))
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// Returns the outputs recorded in the manifest of `out_dir` relative to it, or none if the
+/// directory has no manifest. Entries that would escape the directory are ignored.
+fn read_manifest(out_dir: &Path) -> Vec<PathBuf> {
+    let Ok(manifest) = std::fs::read_to_string(out_dir.join(MANIFEST)) else {
+        return Vec::new();
+    };
+    manifest
+        .lines()
+        .map(PathBuf::from)
+        .filter(|path| {
+            path.components().next().is_some()
+                && path.components().all(|c| matches!(c, Component::Normal(_)))
+        })
+        .collect()
+}
+
+/// Writes the `outputs` relative to `out_dir` into its manifest, one `/`-separated path per line.
+fn write_manifest(out_dir: &Path, outputs: &BTreeSet<PathBuf>) -> Result<(), Error> {
+    std::fs::create_dir_all(out_dir).map_err(|x| Error::from(x).with_path(out_dir))?;
+    let manifest: String = outputs
+        .iter()
+        .map(|path| format!("{}\n", page_name(path)))
+        .collect();
+    write_file(&out_dir.join(MANIFEST), manifest)
+}
+
+/// Writes the HTML of the `parser` into the file at `path` as it is converted, attaching the path
+/// to any error writing the file. The HTML is written into a temporary file next to `path` that
+/// replaces it once converted, so a failed conversion leaves no output behind.
+fn write_html(path: &Path, parser: &Parser, opt: HtmlTranslatorOpt<'_>) -> Result<(), Error> {
+    let mut tmpname = path.file_name().unwrap_or_default().to_os_string();
+    tmpname.push(".tmp");
+    let tmpfile = path.with_file_name(tmpname);
+
+    let result = File::create(&tmpfile)
+        .map_err(|x| Error::from(x).with_path(&tmpfile))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            HtmlString::write_with_opt(parser, opt, &mut writer).map_err(|x| match x {
+                Error::Io { .. } => x.with_path(&tmpfile),
+                x => x,
+            })?;
+            writer
+                .flush()
+                .map_err(|x| Error::from(x).with_path(&tmpfile))
+        })
+        .and_then(|_| std::fs::rename(&tmpfile, path).map_err(|x| Error::from(x).with_path(path)));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmpfile);
+    }
+    result
+}
+
+/// Writes the `contents` to the file at `path`, attaching the path to any error.
+fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    std::fs::write(path, contents).map_err(|x| Error::from(x).with_path(path))
+}
+
+/// Prints the progress of each validated help file to stderr followed by the report as JSON to
+/// stdout, exiting with an error if the report has any problems.
+fn print_validation_report(report: &ValidationReport, helpfiles: &[&str]) -> Result<(), Error> {
+    for helpfile in helpfiles {
+        let errors = report.parse_errors_of(helpfile);
+        eprintln!("validated ({:<4} errors): {helpfile}", errors.len());
+        for error in errors {
+            eprintln!("  {error}");
         }
     }
+    println!("{}", serde_json::to_string_pretty(report)?);
+
+    if report.has_errors() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Returns the table of contents of the parsed vimdoc as JSON.
+fn toc_to_json(parser: &Parser) -> Result<String, Error> {
+    let toc = parser.parse::<Toc>()?;
+    Ok(serde_json::to_string_pretty(&toc)?)
+}
+
+/// Returns the name of the page at `path` as a `/`-separated path.
+fn page_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
 }
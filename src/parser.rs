@@ -1,9 +1,29 @@
+use crate::utils;
+use std::fs::File;
 use std::io;
+use std::ops::Range;
+use std::path::Path;
 
+pub mod ast;
 mod convert;
+mod error;
+mod index;
+mod keycode;
+mod lint;
+mod tagfile;
+mod toc;
+mod validate;
 mod visit;
 
+pub use ast::Document;
 pub use convert::*;
+pub use error::*;
+pub use index::*;
+pub use keycode::*;
+pub use lint::*;
+pub use tagfile::*;
+pub use toc::*;
+pub use validate::*;
 pub use visit::*;
 
 /// Represents the parser of vimdoc that leverages treesitter to construct a tree and then
@@ -19,28 +39,141 @@ pub struct Parser {
 impl Parser {
     /// Loads a new parser to process the `src` using the vimdoc language powered by
     /// [`tree_sitter_vimdoc`].
-    pub fn load_vimdoc<R: io::Read>(src: R) -> io::Result<Self> {
+    pub fn load_vimdoc<R: io::Read>(src: R) -> Result<Self, Error> {
         let language = tree_sitter_vimdoc::language();
         Self::load(src, language)
     }
 
+    /// Loads a new parser to process the vimdoc file at `path`, attaching the path to any error.
+    pub fn load_vimdoc_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::open(path)
+            .map_err(Error::from)
+            .and_then(Self::load_vimdoc)
+            .map_err(|x| x.with_path(path))
+    }
+
+    /// Loads a new parser to process the file at `path` using an existing tree-sitter `parser`,
+    /// attaching the path to any error.
+    pub fn load_file_with(
+        parser: &mut tree_sitter::Parser,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::open(path)
+            .map_err(Error::from)
+            .and_then(|file| Self::load_with(parser, file))
+            .map_err(|x| x.with_path(path))
+    }
+
     /// Loads a new parser to process the `src` using the given `language`.
-    pub fn load<R: io::Read>(src: R, language: tree_sitter::Language) -> io::Result<Self> {
+    pub fn load<R: io::Read>(src: R, language: tree_sitter::Language) -> Result<Self, Error> {
         let mut parser = tree_sitter::Parser::new();
-        parser.set_language(language).unwrap();
+        parser.set_language(language)?;
+        Self::load_with(&mut parser, src)
+    }
+
+    /// Loads a new parser to process the `src` using an existing tree-sitter `parser`, which
+    /// avoids creating a tree-sitter parser per file when loading many files.
+    pub fn load_with<R: io::Read>(parser: &mut tree_sitter::Parser, src: R) -> Result<Self, Error> {
         let src = std::io::read_to_string(src)?;
         let tree = parser
             .parse(&src, None)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Failed to parse vimdoc"))?;
+            .ok_or(Error::Parse { path: None })?;
 
         Ok(Self { src, tree })
     }
 
+    /// Creates a tree-sitter parser for the vimdoc language that can be reused with
+    /// [`Parser::load_with`].
+    pub fn vimdoc_tree_sitter_parser() -> Result<tree_sitter::Parser, Error> {
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(tree_sitter_vimdoc::language())?;
+        Ok(parser)
+    }
+
+    /// Replaces the `range` of bytes within the source with `new_text` and parses the source again,
+    /// reusing the unchanged parts of the existing tree. Returns the ranges of the new tree that
+    /// changed, including the text that was inserted, ordered by position and without overlaps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or does not lie on [`char`] boundaries, like
+    /// [`String::replace_range`].
+    pub fn edit(
+        &mut self,
+        range: Range<usize>,
+        new_text: &str,
+    ) -> Result<Vec<tree_sitter::Range>, Error> {
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(self.tree.language())?;
+        self.edit_with(&mut parser, range, new_text)
+    }
+
+    /// Like [`Parser::edit`], but parses again using an existing tree-sitter `parser`.
+    pub fn edit_with(
+        &mut self,
+        parser: &mut tree_sitter::Parser,
+        range: Range<usize>,
+        new_text: &str,
+    ) -> Result<Vec<tree_sitter::Range>, Error> {
+        let start_position = point_at(&self.src, range.start);
+        let old_end_position = point_at(&self.src, range.end);
+        self.src.replace_range(range.clone(), new_text);
+
+        let new_end_byte = range.start + new_text.len();
+        let edit = tree_sitter::InputEdit {
+            start_byte: range.start,
+            old_end_byte: range.end,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position: point_at(&self.src, new_end_byte),
+        };
+        self.tree.edit(&edit);
+
+        let tree = parser
+            .parse(&self.src, Some(&self.tree))
+            .ok_or(Error::Parse { path: None })?;
+
+        // Tree-sitter only reports ranges whose structure changed, so text replaced within the
+        // same nodes is included as well
+        let mut changed: Vec<tree_sitter::Range> = self.tree.changed_ranges(&tree).collect();
+        changed.push(tree_sitter::Range {
+            start_byte: edit.start_byte,
+            end_byte: edit.new_end_byte,
+            start_point: edit.start_position,
+            end_point: edit.new_end_position,
+        });
+        changed.sort_by_key(|range| range.start_byte);
+        let mut merged: Vec<tree_sitter::Range> = Vec::new();
+        for range in changed {
+            match merged.last_mut() {
+                Some(last) if range.start_byte <= last.end_byte => {
+                    if range.end_byte > last.end_byte {
+                        last.end_byte = range.end_byte;
+                        last.end_point = range.end_point;
+                    }
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        self.tree = tree;
+        Ok(merged)
+    }
+
     /// Returns a reference to the souce being parsed.
     pub fn src(&self) -> &str {
         &self.src
     }
 
+    /// Returns the tag on the first line of the source that names the help file, e.g. `api.txt`
+    /// for `*api.txt*  Nvim`.
+    pub fn title(&self) -> Option<&str> {
+        utils::first_line_tag(&self.src)
+    }
+
     /// Returns a reference to the raw tree representing the source.
     pub fn tree(&self) -> &tree_sitter::Tree {
         &self.tree
@@ -55,3 +188,19 @@ impl Parser {
         F::from_parser(self)
     }
 }
+
+/// Returns the position of the `byte` within `src`, where columns are counted in bytes like
+/// tree-sitter.
+fn point_at(src: &str, byte: usize) -> tree_sitter::Point {
+    let before = &src.as_bytes()[..byte];
+    match before.iter().rposition(|b| *b == b'\n') {
+        Some(newline) => tree_sitter::Point {
+            row: before.iter().filter(|b| **b == b'\n').count(),
+            column: byte - newline - 1,
+        },
+        None => tree_sitter::Point {
+            row: 0,
+            column: byte,
+        },
+    }
+}
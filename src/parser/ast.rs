@@ -0,0 +1,364 @@
+//! Owned representation of vimdoc as Rust types, produced by parsing into a [`Document`].
+
+use super::FromParser;
+use crate::{Error, NodeExt, NodeType, Parser};
+
+/// Help file represented as a series of sections.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document {
+    pub sections: Vec<Section>,
+}
+
+/// Blocks grouped beneath a heading. The first section of a document has no heading when there
+/// is content before the first heading.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Section {
+    pub heading: Option<Heading>,
+    pub blocks: Vec<Block>,
+}
+
+/// Heading of a section.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Heading {
+    /// Level of the heading, where 1 is a heading beneath a `===` separator, 2 is a heading
+    /// beneath a `---` separator, and 3 is an uppercase heading.
+    pub level: u8,
+
+    /// Content of the heading, including any tags.
+    pub inlines: Vec<Inline>,
+}
+
+/// Series of consecutive lines, separated from other blocks by blank lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Block {
+    pub lines: Vec<Line>,
+}
+
+/// Single line within a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// Ordinary line of text, which may start a code block by ending with `>`.
+    Text {
+        inlines: Vec<Inline>,
+        codeblock: Option<CodeBlock>,
+    },
+
+    /// Item of a list, such as `- item`.
+    ListItem(ListItem),
+
+    /// Heading of a column, such as `Column heading ~`.
+    ColumnHeading(Vec<Inline>),
+
+    /// Line that could not be parsed, holding its text as written.
+    Error(String),
+}
+
+/// Item of a list, which may start a code block by ending with `>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListItem {
+    /// Column where the item starts, used to determine nesting.
+    pub indent: usize,
+
+    /// Marker that starts the item, such as `-` or `•`.
+    pub marker: char,
+
+    pub inlines: Vec<Inline>,
+    pub codeblock: Option<CodeBlock>,
+}
+
+/// Preformatted code started by `>` (or `>lang`) and ended by `<` or a non-indented line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// Language of the code, such as `lua` for `>lua`.
+    pub language: Option<String>,
+
+    /// Code as written in the source, including its indentation.
+    pub text: String,
+}
+
+/// Content within a line. Each variant holds its text without any surrounding delimiters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inline {
+    /// Plain word, such as `foo`.
+    Word(String),
+
+    /// Anchor that can be linked to, such as `*foo*`.
+    Tag(String),
+
+    /// Link to a tag, such as `|foo|`.
+    Taglink(String),
+
+    /// Link to an option, such as `'tabstop'`.
+    Optionlink(String),
+
+    /// Inline code, such as `` `foo` ``.
+    Codespan(String),
+
+    /// Key notation, such as `<C-w>` or `CTRL-W`.
+    Keycode(String),
+
+    /// Argument, such as `{foo}`.
+    Argument(String),
+
+    /// Url, such as `https://neovim.io`.
+    Url(String),
+
+    /// Text that could not be parsed.
+    Error(String),
+}
+
+impl FromParser for Document {
+    type Err = Error;
+
+    /// Parses into a document.
+    fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
+        let src = parser.src();
+        let root = parser.tree().root_node();
+        let mut sections = vec![Section::default()];
+
+        let mut cursor = root.walk();
+        for node in root.named_children(&mut cursor) {
+            // Anything other than a block, such as text that could not be parsed, is kept as a
+            // block of its own so no content of the help file is lost
+            let children: Vec<_> = match node.node_type() {
+                Some(NodeType::Block) => node.named_children(&mut node.walk()).collect(),
+                _ => vec![node],
+            };
+
+            // Headings are lines within a block, so they split the block across sections
+            let mut lines = Vec::new();
+            for line in children {
+                if let Some(heading) = to_heading(src, line) {
+                    push_block(&mut sections, std::mem::take(&mut lines));
+                    sections.push(Section {
+                        heading: Some(heading),
+                        blocks: Vec::new(),
+                    });
+                } else {
+                    lines.push(to_line(src, line));
+                }
+            }
+            push_block(&mut sections, lines);
+        }
+
+        // Drop the initial section if the document starts with a heading
+        if sections.len() > 1 && sections[0].blocks.is_empty() {
+            sections.remove(0);
+        }
+
+        Ok(Document { sections })
+    }
+}
+
+/// Adds a block of `lines` to the last section, unless there are no lines.
+fn push_block(sections: &mut [Section], lines: Vec<Line>) {
+    if let (Some(section), false) = (sections.last_mut(), lines.is_empty()) {
+        section.blocks.push(Block { lines });
+    }
+}
+
+/// Returns the text of the `node` within `src`.
+fn text<'a>(src: &'a str, node: tree_sitter::Node) -> &'a str {
+    node.utf8_text(src.as_bytes()).unwrap_or_default()
+}
+
+/// Converts a line into a heading if its content is a heading.
+fn to_heading(src: &str, line: tree_sitter::Node) -> Option<Heading> {
+    if !matches!(line.node_type(), Some(NodeType::Line)) {
+        return None;
+    }
+
+    let node = line.named_child(0)?;
+    let level = match node.node_type()? {
+        NodeType::H1 => 1,
+        NodeType::H2 => 2,
+        NodeType::H3 => 3,
+        _ => return None,
+    };
+
+    let (inlines, _) = to_inlines(src, node);
+    Some(Heading { level, inlines })
+}
+
+/// Converts a `line` or `line_li` node into a line. Any other node, such as an `ERROR`, is kept as
+/// the text of a line that could not be parsed.
+fn to_line(src: &str, node: tree_sitter::Node) -> Line {
+    match node.node_type() {
+        _ if node.is_error() || node.is_missing() => Line::Error(text(src, node).to_string()),
+        Some(NodeType::Line) => match node.named_child(0) {
+            Some(child) if matches!(child.node_type(), Some(NodeType::ColumnHeading)) => {
+                Line::ColumnHeading(to_inlines(src, child).0)
+            }
+            _ => {
+                let (inlines, codeblock) = to_inlines(src, node);
+                Line::Text { inlines, codeblock }
+            }
+        },
+        Some(NodeType::LineLi) => {
+            let (inlines, codeblock) = to_inlines(src, node);
+            let marker = text(src, node)
+                .trim_start_matches('<')
+                .trim_start()
+                .chars()
+                .next()
+                .unwrap_or('-');
+            Line::ListItem(ListItem {
+                indent: node.start_position().column,
+                marker,
+                inlines,
+                codeblock,
+            })
+        }
+        _ => Line::Error(text(src, node).to_string()),
+    }
+}
+
+/// Converts the named children of the `node` into inlines, returning any code block started
+/// at the end of the node.
+fn to_inlines(src: &str, node: tree_sitter::Node) -> (Vec<Inline>, Option<CodeBlock>) {
+    let mut inlines = Vec::new();
+    let mut codeblock = None;
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let raw = text(src, child).trim();
+        let inline = match child.node_type() {
+            Some(NodeType::Codeblock) => {
+                codeblock = Some(to_codeblock(src, child));
+                continue;
+            }
+            _ if child.is_error() || child.is_missing() => Inline::Error(raw.to_string()),
+            Some(NodeType::Tag) => Inline::Tag(raw.trim_matches('*').to_string()),
+            Some(NodeType::Taglink) => Inline::Taglink(raw.trim_matches('|').to_string()),
+            Some(NodeType::Optionlink) => Inline::Optionlink(raw.trim_matches('\'').to_string()),
+            Some(NodeType::Codespan) => Inline::Codespan(raw.trim_matches('`').to_string()),
+            Some(NodeType::Keycode) => Inline::Keycode(raw.to_string()),
+            Some(NodeType::Argument) => Inline::Argument(
+                raw.trim_start_matches('{')
+                    .trim_end_matches('}')
+                    .to_string(),
+            ),
+            Some(NodeType::Url) => Inline::Url(raw.to_string()),
+            _ => Inline::Word(raw.to_string()),
+        };
+        inlines.push(inline);
+    }
+
+    (inlines, codeblock)
+}
+
+/// Converts a `codeblock` node into a code block.
+fn to_codeblock(src: &str, node: tree_sitter::Node) -> CodeBlock {
+    let mut language = None;
+    let mut code = String::new();
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.node_type() {
+            Some(NodeType::Language) => language = Some(text(src, child).trim().to_string()),
+            Some(NodeType::Code) => code = text(src, child).to_string(),
+            _ => {}
+        }
+    }
+
+    CodeBlock {
+        language,
+        text: code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELP_FILE: &str = "*test.txt*\tTest
+
+==============================================================================
+INTRODUCTION\t\t\t\t\t\t\t*test-intro*
+
+- first item
+- second |test-intro|
+
+Example: >lua
+    print('hi')
+<
+";
+
+    fn document(src: &str) -> Document {
+        let parser = Parser::load_vimdoc(src.as_bytes()).unwrap();
+        parser.parse::<Document>().unwrap()
+    }
+
+    #[test]
+    fn should_split_sections_at_headings() {
+        let document = document(HELP_FILE);
+        assert_eq!(document.sections.len(), 2, "{document:#?}");
+
+        let title = &document.sections[0];
+        assert_eq!(title.heading, None);
+        assert_eq!(
+            title.blocks,
+            [Block {
+                lines: vec![Line::Text {
+                    inlines: vec![
+                        Inline::Tag(String::from("test.txt")),
+                        Inline::Word(String::from("Test")),
+                    ],
+                    codeblock: None,
+                }],
+            }]
+        );
+
+        let heading = document.sections[1].heading.as_ref().unwrap();
+        assert_eq!(heading.level, 1);
+        assert!(
+            heading
+                .inlines
+                .contains(&Inline::Tag(String::from("test-intro"))),
+            "{heading:#?}"
+        );
+    }
+
+    #[test]
+    fn should_represent_list_items() {
+        let document = document(HELP_FILE);
+        let list = &document.sections[1].blocks[0];
+        assert_eq!(
+            list.lines,
+            [
+                Line::ListItem(ListItem {
+                    indent: 0,
+                    marker: '-',
+                    inlines: vec![
+                        Inline::Word(String::from("first")),
+                        Inline::Word(String::from("item")),
+                    ],
+                    codeblock: None,
+                }),
+                Line::ListItem(ListItem {
+                    indent: 0,
+                    marker: '-',
+                    inlines: vec![
+                        Inline::Word(String::from("second")),
+                        Inline::Taglink(String::from("test-intro")),
+                    ],
+                    codeblock: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_represent_codeblocks() {
+        let document = document(HELP_FILE);
+        let example = &document.sections[1].blocks[1];
+        let Line::Text { inlines, codeblock } = &example.lines[0] else {
+            panic!("Expected a line of text: {example:#?}");
+        };
+        assert_eq!(inlines, &[Inline::Word(String::from("Example:"))]);
+
+        let codeblock = codeblock.as_ref().unwrap();
+        assert_eq!(codeblock.language.as_deref(), Some("lua"));
+        assert_eq!(codeblock.text.trim_end(), "    print('hi')");
+    }
+}
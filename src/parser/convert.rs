@@ -1,11 +1,22 @@
 mod debug;
 mod html;
+mod json;
+mod markdown;
+mod vimdoc;
 
 pub use debug::DebugString;
-pub use html::HtmlString;
+pub use html::{
+    HtmlDocumentOpt, HtmlString, HtmlTemplate, HtmlTheme, HtmlTranslator, HtmlTranslatorOpt,
+    PageLink,
+};
+pub use json::{JsonAst, JsonNode, JsonPosition};
+pub use markdown::{MarkdownString, MarkdownTranslator, MarkdownTranslatorOpt};
+pub use vimdoc::VimdocString;
 
 use crate::utils;
-use crate::{Context, Joiner, NodeType, Parser, Visitor};
+use crate::{Context, Error, Heading, NodeExt, NodeType, Parser, Toc, Visitor};
+use std::cmp::Ordering;
+use std::io;
 
 /// Parse a value from a [`Parser`].
 pub trait FromParser: Sized {
@@ -14,75 +25,297 @@ pub trait FromParser: Sized {
     fn from_parser(parser: &Parser) -> Result<Self, Self::Err>;
 }
 
+/// Interface to translate vimdoc nodes into some other form. Each method is provided the
+/// [`Context`] of the node being translated, the shared [`ConverterState`], and the `text`
+/// produced by joining the translated children of the node (or the escaped text of the node
+/// itself if it has no children).
+///
+/// Handling of errors, noise, and blank content is performed by the [`Converter`] prior to
+/// invoking any of these methods.
 pub trait VimdocTranslator {
     type Output;
 
-    fn argument<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn block<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn code<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn codeblock<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn codespan<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn column_heading<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn h1<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn h2<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn h3<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn help_file<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn keycode<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn language<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn line<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn line_li<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn optionlink<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn tag<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn taglink<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn uppercase_name<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn url<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-    fn word<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output;
-}
+    /// Joins the outputs of multiple sibling nodes together.
+    fn join(&self, outputs: Vec<Self::Output>) -> Self::Output;
+
+    /// Returns the separator that [`join`](Self::join) places before the `next` output, where
+    /// `prev` is the last non-empty output before it or `None` if `next` is the first output. Used
+    /// to join outputs as they are written rather than all at once.
+    ///
+    /// By default, outputs are separated by a space, matching a [`join`](Self::join) that uses
+    /// [`SPACE_STRING_JOINER`](crate::SPACE_STRING_JOINER).
+    fn separator(&self, prev: Option<&Self::Output>, _next: &Self::Output) -> &'static str {
+        match prev {
+            Some(_) => " ",
+            None => "",
+        }
+    }
 
-/// Options for the convert visitor.
-#[derive(Clone, Debug)]
-pub struct ConverterOpt<T> {
-    pub joiner: T,
-    pub old: bool,
+    /// Escapes raw text from the source so it can be included in the output.
+    fn escape(&self, text: &str) -> Self::Output;
+
+    fn argument<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn block<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn code<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn codeblock<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn codespan<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn column_heading<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn h1<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn h2<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn h3<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn help_file<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn keycode<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn language<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn line<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn line_li<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn optionlink<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn tag<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn taglink<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn uppercase_name<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn url<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
+    fn word<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output;
 }
 
 /// State for the convert visitor.
 #[derive(Debug, Default)]
 pub struct ConverterState {
+    /// Language of the most recent `>lang` marker, consumed by the next code node.
     pub language: Option<String>,
+
+    /// Current indentation level of list items, starting at 1.
+    pub indent: usize,
+
+    /// Table of contents built from the headings visited so far.
+    pub toc: Toc,
 }
 
-/// Used to convert into some other form by navigating a vimdoc tree.
+/// Used to convert into some other form by navigating a vimdoc tree, delegating the translation
+/// of each node to a [`VimdocTranslator`].
 pub struct Converter<T> {
-    opt: ConverterOpt<T>,
+    translator: T,
     state: ConverterState,
+
+    /// First node encountered that could not be converted.
+    error: Option<Error>,
 }
 
 impl<T> Converter<T> {
-    pub fn new(opt: ConverterOpt<T>) -> Self {
+    pub fn new(translator: T) -> Self {
         Self {
-            opt,
+            translator,
             state: ConverterState::default(),
+            error: None,
+        }
+    }
+
+    /// Takes the error of the first node that could not be converted, if any. Unsupported nodes
+    /// are skipped so the rest of the tree is still visited, but the output is then incomplete, so
+    /// conversions fail with this error rather than producing the output.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Returns a reference to the translator used by the converter.
+    pub fn translator(&self) -> &T {
+        &self.translator
+    }
+
+    /// Returns a reference to the state of the converter.
+    pub fn state(&self) -> &ConverterState {
+        &self.state
+    }
+
+    /// Converts the node of the [`Context`] like [`Visitor::visit`], except that the output of
+    /// each of its children is written to the `writer` as soon as the child is converted instead
+    /// of joining the output of the entire tree in memory. The node itself is not translated, so
+    /// this is meant for the root of a help file whose translation does not alter its children.
+    ///
+    /// Every node is visited exactly once. Each child is still converted into memory as a whole,
+    /// including the joined output of its descendants, so the most held at a time is the output of
+    /// the largest child (usually a single block) rather than that of the entire help file.
+    pub fn write_to<W: io::Write>(
+        &mut self,
+        ctx: &mut Context<'_, '_, '_>,
+        writer: &mut W,
+    ) -> io::Result<()>
+    where
+        T: VimdocTranslator<Output = String>,
+    {
+        // Nodes with errors are written as their raw text, which requires the entire node
+        if ctx.has_error() || !ctx.has_children() {
+            let output = self.visit(ctx);
+            return writer.write_all(output.as_bytes());
+        }
+
+        let mut prev: Option<String> = None;
+        let mut has_next = ctx.goto_first_child();
+        while has_next {
+            if ctx.node().is_named() {
+                let output = self.visit(ctx);
+                let separator = self.translator.separator(prev.as_ref(), &output);
+                writer.write_all(separator.as_bytes())?;
+                writer.write_all(output.as_bytes())?;
+
+                if prev.is_none() || !output.is_empty() {
+                    prev = Some(output);
+                }
+            }
+
+            has_next = ctx.goto_next_sibling();
+            if !has_next {
+                ctx.goto_parent();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates the list item indentation based on the previous list item sibling, if any.
+    fn update_indent(&mut self, ctx: &Context<'_, '_, '_>) {
+        let prev_li = ctx
+            .node()
+            .prev_named_sibling()
+            .filter(|node| matches!(node.node_type(), Some(NodeType::LineLi)));
+
+        // The previous list item is logically the parent if it is indented less
+        match prev_li {
+            Some(prev_li) => {
+                // Measured like `ListItem::indent` of the AST so both agree on nesting
+                let parent_indent = prev_li.start_position().column;
+                let this_indent = ctx.node().start_position().column;
+                match this_indent.cmp(&parent_indent) {
+                    Ordering::Greater => self.state.indent += 1,
+                    Ordering::Less => {
+                        self.state.indent = self.state.indent.saturating_sub(1).max(1)
+                    }
+                    Ordering::Equal => {}
+                }
+            }
+            None => self.state.indent = 1,
         }
     }
 }
 
-impl<T: Joiner<Output = String>> Visitor for Converter<T> {
+impl<T: VimdocTranslator<Output = String>> Visitor for Converter<T> {
     type Output = String;
 
     fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
         let has_error = ctx.has_error();
         let text = if !ctx.has_children() || has_error {
-            ctx.node_clean_text()
+            self.translator.escape(ctx.node_raw_text())
         } else {
-            self.opt.joiner.join(self.visit_children_named(ctx))
+            let outputs = self.visit_children_named(ctx);
+            self.translator.join(outputs)
         };
         let trimmed_text = text.trim_start();
 
         if let Some(node_type) = ctx.node_type() {
+            let t = &mut self.translator;
+            let state = &self.state;
+
             match node_type {
                 ///////////////////////////////////////////////////////////
-                // NON-HTML GENERATION (PLAIN TEXT, ERROR HANDLING, ETC)
+                // ERROR, NOISE, AND BLANK HANDLING
                 ///////////////////////////////////////////////////////////
                 NodeType::Block | NodeType::Code if utils::is_blank(&text) => String::new(),
                 NodeType::ColumnHeading
@@ -96,53 +329,57 @@ impl<T: Joiner<Output = String>> Visitor for Converter<T> {
                 NodeType::H1 | NodeType::H2 | NodeType::H3 if utils::is_noise(&text) => {
                     String::new()
                 }
+                NodeType::Line
+                    if !matches!(
+                        ctx.parent_node_type(),
+                        Some(NodeType::Code | NodeType::Codeblock)
+                    ) && (utils::is_blank(&text) || utils::is_noise(ctx.node_raw_text())) =>
+                {
+                    String::new()
+                }
 
                 ///////////////////////////////////////////////////////////
-                // HTML GENERATION
+                // TRANSLATION
                 ///////////////////////////////////////////////////////////
-                NodeType::Argument => format!(r"<code>{text}</code>"),
-                NodeType::Block if self.opt.old => {
-                    format!(r#"<div class="old-help-para">{}</div>\n"#, text.trim_end())
-                }
-                NodeType::Block => format!(r#"<div class="help-para">\n{text}\n</div>\n"#),
+                NodeType::Argument => t.argument(ctx, state, text),
+                NodeType::Block => t.block(ctx, state, text),
                 NodeType::Code => {
-                    let text = utils::trim_indent(&text, /* tab=8space */ 8);
-                    let trimmed = text.trim_end();
-                    match self.state.language.take() {
-                        Some(language) => {
-                            format!(
-                                r#"<pre><code class="language-{language}">{trimmed}</code></pre>"#
-                            )
-                        }
-                        None => format!("<pre>{trimmed}</pre>"),
-                    }
+                    let out = t.code(ctx, state, text);
+                    self.state.language = None;
+                    out
+                }
+                NodeType::Codeblock => t.codeblock(ctx, state, text),
+                NodeType::Codespan => t.codespan(ctx, state, text),
+                NodeType::ColumnHeading => t.column_heading(ctx, state, text),
+                NodeType::H1 => {
+                    self.state.toc.insert(Heading::from_ctx(ctx), true);
+                    self.translator.h1(ctx, &self.state, text)
+                }
+                NodeType::H2 => {
+                    self.state.toc.insert(Heading::from_ctx(ctx), false);
+                    self.translator.h2(ctx, &self.state, text)
                 }
-                NodeType::Codeblock => text,
-                NodeType::Codespan if self.opt.old => todo!(),
-                NodeType::Codespan => format!("<code>{trimmed_text}</code>"),
-                NodeType::ColumnHeading => {
-                    format!(r#"<div class="help-column_heading">{text}</div>"#)
+                NodeType::H3 => {
+                    self.state.toc.insert(Heading::from_ctx(ctx), false);
+                    self.translator.h3(ctx, &self.state, text)
                 }
-                NodeType::H1 => todo!(),
-                NodeType::H2 => todo!(),
-                NodeType::H3 => todo!(),
-                NodeType::HelpFile => text,
-                NodeType::Keycode => format!("<code>{trimmed_text}</code>"),
+                NodeType::HelpFile => t.help_file(ctx, state, text),
+                NodeType::Keycode => t.keycode(ctx, state, text),
                 NodeType::Language => {
                     self.state.language = Some(ctx.node_raw_text().to_string());
-                    String::new()
+                    self.translator.language(ctx, &self.state, text)
                 }
-                NodeType::Line => todo!(),
-                NodeType::LineLi => todo!(),
-                NodeType::Optionlink => todo!(),
-                NodeType::Tag => todo!(),
-                NodeType::Taglink => todo!(),
-                NodeType::UppercaseName => text,
-                NodeType::Url => {
-                    let (href, remaining) = utils::fix_url(trimmed_text);
-                    format!(r#"<a href="{href}">{href}</a>{remaining}"#)
+                NodeType::Line => t.line(ctx, state, text),
+                NodeType::LineLi => {
+                    self.update_indent(ctx);
+                    self.translator.line_li(ctx, &self.state, text)
                 }
-                NodeType::Word => text,
+                NodeType::Optionlink => t.optionlink(ctx, state, text),
+                NodeType::Tag => t.tag(ctx, state, text),
+                NodeType::Taglink => t.taglink(ctx, state, text),
+                NodeType::UppercaseName => t.uppercase_name(ctx, state, text),
+                NodeType::Url => t.url(ctx, state, text),
+                NodeType::Word => t.word(ctx, state, text),
             }
         } else if has_error && utils::ignore_parse_error(trimmed_text) {
             text
@@ -150,7 +387,63 @@ impl<T: Joiner<Output = String>> Visitor for Converter<T> {
             let text = utils::truncate_str(&text, 10);
             format!(r#"{{ERROR: {text}}}"#)
         } else {
+            let node = ctx.node();
+            self.error.get_or_insert_with(|| Error::UnsupportedNode {
+                path: None,
+                kind: node.kind().to_string(),
+                position: node.start_position(),
+            });
             String::new()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Document, Line};
+
+    const NESTED_LIST: &str = "*list.txt*\tList fixture
+
+- first
+  - nested
+    - deeper
+  - nested again
+- last
+";
+
+    #[test]
+    fn should_nest_list_items_like_ast() {
+        let parser = Parser::load_vimdoc(NESTED_LIST.as_bytes()).unwrap();
+
+        let document = parser.parse::<Document>().unwrap();
+        let indents: Vec<usize> = document
+            .sections
+            .iter()
+            .flat_map(|section| &section.blocks)
+            .flat_map(|block| &block.lines)
+            .filter_map(|line| match line {
+                Line::ListItem(item) => Some(item.indent),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(indents, [0, 2, 4, 2, 0]);
+
+        let html = parser.parse::<HtmlString>().unwrap();
+        let margins: Vec<&str> = html
+            .split(r#"<div class="help-li" style=""#)
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        assert_eq!(
+            margins,
+            [
+                "",
+                "margin-left: 3rem;",
+                "margin-left: 4.5rem;",
+                "margin-left: 3rem;",
+                "",
+            ]
+        );
+    }
+}
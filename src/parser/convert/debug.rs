@@ -1,6 +1,6 @@
 use super::{FromParser, Parser};
 use crate::utils;
-use crate::{visitor, Context, NodeExt, Visitor, NEWLINE_STRING_JOINER};
+use crate::{visitor, Context, Error, Visitor, NEWLINE_STRING_JOINER};
 use std::ops::{Deref, DerefMut};
 
 /// Newtype [`String`] representing debug output from a [`Parser`].
@@ -27,13 +27,13 @@ impl DerefMut for DebugString {
 }
 
 impl FromParser for DebugString {
-    type Err = ();
+    type Err = Error;
 
     /// Parses into a debug string.
     fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
         let mut visitor = visitor!(|_this, ctx| -> String {
             let node = ctx.node();
-            let depth = node.depth();
+            let depth = ctx.depth();
             let node_text = ctx.node_raw_text();
             let is_too_long = node_text.len() > 10;
 
@@ -54,10 +54,7 @@ impl FromParser for DebugString {
         });
 
         Ok(DebugString(visitor.visit_all_named(
-            &mut Context {
-                src: parser.src(),
-                cursor: &mut parser.tree().walk(),
-            },
+            &mut Context::new(parser.src(), &mut parser.tree().walk()),
             &NEWLINE_STRING_JOINER,
         )))
     }
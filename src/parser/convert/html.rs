@@ -110,11 +110,37 @@
  *     margin-left (css) = 1.5 * opt.indent (if > 1)
  *     return <div class="help-li" style="margin-left:...">{text}</div>
  */
-use super::{FromParser, Parser};
+#[cfg(feature = "highlight")]
+mod highlight;
+mod template;
+mod theme;
+
+pub use template::{HtmlDocumentOpt, HtmlTemplate, PageLink};
+pub use theme::HtmlTheme;
+
+use super::{ConverterState, FromParser, Parser, VimdocTranslator};
 use crate::utils;
-use crate::{Context, NodeType, Visitor, SPACE_STRING_JOINER};
+use crate::{
+    Context, Converter, Error, Heading, Joiner, Keycode, NodeExt, NodeType, TagIndex, Toc,
+    SPACE_STRING_JOINER,
+};
+use std::io;
 use std::ops::{Deref, DerefMut};
 
+/// Returns the code block being visited as highlighted HTML, or none if its `language` cannot be
+/// highlighted.
+#[cfg(feature = "highlight")]
+fn highlight_code(ctx: &Context<'_, '_, '_>, language: &str) -> Option<String> {
+    let code = utils::trim_indent(ctx.node_raw_text(), /* tab=8space */ 8);
+    highlight::highlight(language, code.trim_end())
+}
+
+/// Returns none as highlighting code blocks requires the `highlight` feature.
+#[cfg(not(feature = "highlight"))]
+fn highlight_code(_ctx: &Context<'_, '_, '_>, _language: &str) -> Option<String> {
+    None
+}
+
 /// Newtype [`String`] representing HTML output from a [`Parser`].
 pub struct HtmlString(String);
 
@@ -138,19 +164,460 @@ impl DerefMut for HtmlString {
     }
 }
 
+impl HtmlString {
+    /// Parses into an HTML string using the provided options, failing if the tree contains a node
+    /// that cannot be converted.
+    pub fn from_parser_with_opt(
+        parser: &Parser,
+        opt: HtmlTranslatorOpt<'_>,
+    ) -> Result<Self, Error> {
+        let mut out = Vec::new();
+        Self::write_with_opt(parser, opt, &mut out)?;
+        let html =
+            String::from_utf8(out).map_err(|x| io::Error::new(io::ErrorKind::InvalidData, x))?;
+        Ok(HtmlString(html))
+    }
+
+    /// Writes HTML to the `writer` using the provided options, writing each block of the help file
+    /// as soon as it is converted rather than holding the HTML of the entire help file in memory.
+    /// Fails if the `writer` fails or the tree contains a node that cannot be converted, in which
+    /// case the `writer` may already hold part of the HTML, which should be discarded.
+    pub fn write_with_opt<W: io::Write>(
+        parser: &Parser,
+        mut opt: HtmlTranslatorOpt<'_>,
+        writer: &mut W,
+    ) -> Result<(), Error> {
+        // The table of contents comes before the body, so its headings are collected in a first
+        // pass that walks the tree without producing any output
+        let toc = if opt.toc {
+            parser.parse::<Toc>()?
+        } else {
+            Toc::new()
+        };
+        let toc = match toc {
+            toc if !toc.is_empty() => toc_html(&toc),
+            _ => String::new(),
+        };
+        let document = opt.document.take();
+        let mut converter = Converter::new(HtmlTranslator::new(opt));
+        let mut write_body = |writer: &mut W| {
+            converter.write_to(
+                &mut Context::new(parser.src(), &mut parser.tree().walk()),
+                writer,
+            )
+        };
+
+        match document {
+            Some(document) => document.write_to(parser.src(), &toc, writer, write_body)?,
+            None => {
+                writer.write_all(toc.as_bytes())?;
+                write_body(writer)?;
+            }
+        }
+
+        match converter.take_error() {
+            Some(x) => Err(x),
+            None => Ok(()),
+        }
+    }
+}
+
 impl FromParser for HtmlString {
-    type Err = ();
+    type Err = Error;
 
     /// Parses into an HTML string.
     fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
-        let mut visitor = HtmlVisitor::new(HtmlVisitorOpt { old: false });
-
-        Ok(HtmlString(visitor.visit_all_named(
-            &mut Context {
-                src: parser.src(),
-                cursor: &mut parser.tree().walk(),
-            },
-            &SPACE_STRING_JOINER,
-        )))
+        Self::from_parser_with_opt(parser, HtmlTranslatorOpt::default())
+    }
+}
+
+/// Options for the HTML translator.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlTranslatorOpt<'a> {
+    /// If true, uses the old (preformatted) layout for paragraphs.
+    pub old: bool,
+
+    /// Index of tags across all pages, used to resolve taglinks to other pages. If not provided,
+    /// all taglinks are assumed to refer to the current page.
+    pub tags: Option<&'a TagIndex>,
+
+    /// Page being generated as a `/`-separated path, used to produce relative links to other
+    /// pages in [`TagIndex`].
+    pub page: Option<String>,
+
+    /// If true, a `<nav>` table of contents is included at the top of the page.
+    pub toc: bool,
+
+    /// If true, each key of a keycode links to the tag of its notation like `<CR>`, or otherwise
+    /// to `key-notation`. Keys are only linked to tags found within [`TagIndex`].
+    pub link_keycodes: bool,
+
+    /// If provided, the HTML is wrapped into a standalone document; otherwise, only an HTML
+    /// fragment is produced.
+    pub document: Option<HtmlDocumentOpt>,
+}
+
+/// Renders the table of contents as a nested list of links within a `<nav>`.
+fn toc_html(toc: &Toc) -> String {
+    fn list_html(headings: &[Heading]) -> String {
+        let items: String = headings
+            .iter()
+            .map(|heading| {
+                let subheadings = if heading.subheadings.is_empty() {
+                    String::new()
+                } else {
+                    list_html(&heading.subheadings)
+                };
+                format!(
+                    "<li><a href=\"#{}\">{}</a>{subheadings}</li>\n",
+                    utils::url_encode(&heading.tag),
+                    utils::escape_html(&heading.name)
+                )
+            })
+            .collect();
+        format!("<ul>\n{items}</ul>\n")
+    }
+
+    format!(
+        "<nav class=\"help-toc\">\n{}</nav>\n",
+        list_html(&toc.headings)
+    )
+}
+
+/// Implementation of [`VimdocTranslator`] that produces HTML.
+pub struct HtmlTranslator<'a> {
+    opt: HtmlTranslatorOpt<'a>,
+}
+
+impl<'a> HtmlTranslator<'a> {
+    pub fn new(opt: HtmlTranslatorOpt<'a>) -> Self {
+        Self { opt }
+    }
+
+    /// Returns the `<a name>` anchor and the `<h2>`/`<h3>` heading for the node being visited.
+    fn heading(
+        &self,
+        ctx: &Context<'_, '_, '_>,
+        state: &ConverterState,
+        el: &str,
+        text: String,
+    ) -> String {
+        let node = ctx.node();
+        let mut cursor = node.walk();
+        let has_tag = node
+            .named_children(&mut cursor)
+            .any(|node| matches!(node.node_type(), Some(NodeType::Tag)));
+
+        // If there is a *tag* within the heading, it already provides the anchor
+        let anchor = match state.toc.last() {
+            Some(heading) if !has_tag => {
+                format!(r#"<a name="{}"></a>"#, utils::url_encode(&heading.tag))
+            }
+            _ => String::new(),
+        };
+
+        format!("{anchor}<{el} class=\"help-heading\">{text}</{el}>\n")
+    }
+
+    /// Returns an `<a href>` to the tag, displaying the tag itself.
+    fn link(&self, ctx: &Context<'_, '_, '_>, tagname: &str) -> String {
+        let href = self
+            .opt
+            .tags
+            .and_then(|tags| tags.href(tagname, self.opt.page.as_deref()))
+            .unwrap_or_else(|| format!("#{}", utils::url_encode(tagname)));
+        format!(
+            r#"<a href="{}">{}</a>"#,
+            utils::escape_html_attribute(&href),
+            ctx.clean_text(tagname)
+        )
+    }
+
+    /// Returns the `keycode` as nested `<kbd>` elements, one per key and modifier, titled with the
+    /// keys in readable form like `Ctrl+W, Ctrl+V`.
+    fn kbd(&self, keycode: &Keycode) -> String {
+        let keys: Vec<String> = keycode
+            .keys
+            .iter()
+            .map(|key| {
+                let mut html = String::new();
+                for modifier in key.modifiers.iter() {
+                    html.push_str(&format!("<kbd>{modifier}</kbd>+"));
+                }
+                html.push_str(&format!("<kbd>{}</kbd>", utils::escape_html(&key.name)));
+
+                // Tags like `<CR>` describe the key itself, whereas tags like `CTRL-V` describe a
+                // command, so only the former are linked in place of `key-notation`
+                let href = self
+                    .opt
+                    .tags
+                    .filter(|_| self.opt.link_keycodes)
+                    .and_then(|tags| {
+                        let page = self.opt.page.as_deref();
+                        tags.href(&key.notation, page)
+                            .filter(|_| key.notation.starts_with('<'))
+                            .or_else(|| tags.href("key-notation", page))
+                    });
+                match href {
+                    Some(href) => format!(
+                        r#"<a href="{}">{html}</a>"#,
+                        utils::escape_html_attribute(&href)
+                    ),
+                    None => html,
+                }
+            })
+            .collect();
+
+        format!(
+            r#"<kbd class="help-keycode" title="{}">{}</kbd>"#,
+            utils::escape_html_attribute(&keycode.to_string()),
+            keys.join(" ")
+        )
+    }
+}
+
+impl VimdocTranslator for HtmlTranslator<'_> {
+    type Output = String;
+
+    fn join(&self, outputs: Vec<Self::Output>) -> Self::Output {
+        SPACE_STRING_JOINER.join(outputs)
+    }
+
+    fn escape(&self, text: &str) -> Self::Output {
+        utils::escape_html(text)
+    }
+
+    fn argument<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        format!("<code>{text}</code>")
+    }
+
+    fn block<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        if self.opt.old {
+            format!("<div class=\"old-help-para\">{}</div>\n", text.trim_end())
+        } else {
+            format!("<div class=\"help-para\">\n{text}\n</div>\n")
+        }
+    }
+
+    fn code<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        let text = utils::trim_indent(&text, /* tab=8space */ 8);
+        let trimmed = text.trim_end();
+        match state.language.as_deref() {
+            Some(language) => {
+                let code = highlight_code(ctx, language);
+                let trimmed = code.as_deref().unwrap_or(trimmed);
+                format!(r#"<pre><code class="language-{language}">{trimmed}</code></pre>"#)
+            }
+            None => format!("<pre>{trimmed}</pre>"),
+        }
+    }
+
+    fn codeblock<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
+    }
+
+    fn codespan<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        format!("<code>{}</code>", text.trim_start())
+    }
+
+    fn column_heading<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        format!(r#"<div class="help-column_heading">{text}</div>"#)
+    }
+
+    fn h1<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        self.heading(ctx, state, "h2", text)
+    }
+
+    fn h2<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        self.heading(ctx, state, "h3", text)
+    }
+
+    fn h3<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        self.heading(ctx, state, "h3", text)
+    }
+
+    fn help_file<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
+    }
+
+    fn keycode<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        match ctx.node_raw_text().parse::<Keycode>() {
+            Ok(keycode) => self.kbd(&keycode),
+            Err(_) => format!("<code>{}</code>", text.trim_start()),
+        }
+    }
+
+    fn language<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        String::new()
+    }
+
+    fn line<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        let starts_with_heading = matches!(
+            ctx.node().named_child(0).and_then(|node| node.node_type()),
+            Some(NodeType::ColumnHeading | NodeType::H1 | NodeType::H2 | NodeType::H3)
+        );
+
+        // Avoid newlines (too much whitespace) after block elements in old layout
+        if self.opt.old && starts_with_heading {
+            text.trim().to_string()
+        } else {
+            format!("{text}\n")
+        }
+    }
+
+    fn line_li<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        let margin = if state.indent > 1 {
+            format!("margin-left: {}rem;", 1.5 * state.indent as f32)
+        } else {
+            String::new()
+        };
+        format!(r#"<div class="help-li" style="{margin}">{text}</div>"#)
+    }
+
+    fn optionlink<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        // Optionlinks keep their quotes as part of the tag, e.g. 'tabstop'
+        self.link(ctx, ctx.node_raw_text().trim())
+    }
+
+    fn tag<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        let node = ctx.node();
+        let tagname = ctx.node_raw_text().trim().trim_matches('*');
+        let in_heading = matches!(ctx.parent_node_type(), Some(NodeType::H1 | NodeType::H2));
+        let el = if in_heading { "span" } else { "code" };
+        let mut s = format!(
+            r#"<a name="{}"></a><{el} class="help-tag">{}</{el}>"#,
+            utils::url_encode(tagname),
+            ctx.clean_text(tagname)
+        );
+
+        // Wrap consecutive tags in a heading within a container so they can be right-aligned,
+        // e.g. <h2>foo bar<span>tag1 tag2</span></h2>
+        if in_heading {
+            if !matches!(node.prev_sibling_node_type(), Some(NodeType::Tag)) {
+                s.insert_str(0, r#"<span class="help-heading-tags">"#);
+            }
+            if node.next_named_sibling().is_none() {
+                s.push_str("</span>");
+            }
+        }
+
+        s
+    }
+
+    fn taglink<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        self.link(ctx, ctx.node_raw_text().trim().trim_matches('|'))
+    }
+
+    fn uppercase_name<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
+    }
+
+    fn url<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        let (href, remaining) = utils::fix_url(text.trim_start());
+        format!(r#"<a href="{href}">{href}</a>{remaining}"#)
+    }
+
+    fn word<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
     }
 }
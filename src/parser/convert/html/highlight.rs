@@ -0,0 +1,119 @@
+use crate::utils;
+use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter};
+
+const BASH_HIGHLIGHTS: &str = include_str!("highlight/bash.scm");
+const LUA_HIGHLIGHTS: &str = include_str!("highlight/lua.scm");
+const QUERY_HIGHLIGHTS: &str = include_str!("highlight/query.scm");
+const VIM_HIGHLIGHTS: &str = include_str!("highlight/vim.scm");
+
+/// Names of highlights styled by the bundled themes, each rendered as a class like `hl-keyword`.
+/// Captures of a query are matched to the longest name they start with, so `@function.builtin` is
+/// rendered as `hl-function`.
+const HIGHLIGHT_NAMES: &[&str] = &[
+    "comment", "constant", "function", "keyword", "label", "number", "operator", "property",
+    "string", "type", "variable",
+];
+
+/// Highlight configuration of each bundled language. Languages whose query fails to load are left
+/// out, so their code is not highlighted rather than failing the conversion.
+static CONFIGS: Lazy<HashMap<&'static str, HighlightConfiguration>> = Lazy::new(|| {
+    let languages = [
+        ("bash", tree_sitter_bash::language(), BASH_HIGHLIGHTS),
+        (
+            "c",
+            tree_sitter_c::language(),
+            tree_sitter_c::HIGHLIGHT_QUERY,
+        ),
+        ("lua", tree_sitter_lua::language(), LUA_HIGHLIGHTS),
+        ("query", tree_sitter_query::language(), QUERY_HIGHLIGHTS),
+        ("vim", tree_sitter_vim::language(), VIM_HIGHLIGHTS),
+    ];
+
+    languages
+        .into_iter()
+        .filter_map(|(name, language, highlights)| {
+            let mut config = HighlightConfiguration::new(language, highlights, "", "").ok()?;
+            config.configure(HIGHLIGHT_NAMES);
+            Some((name, config))
+        })
+        .collect()
+});
+
+thread_local! {
+    /// Highlighter reused for every code block converted by the same thread.
+    static HIGHLIGHTER: RefCell<Highlighter> = RefCell::new(Highlighter::new());
+}
+
+/// Returns the configuration of the `language` of a code block, e.g. `sh` from `>sh`.
+fn config(language: &str) -> Option<&'static HighlightConfiguration> {
+    let name = match language {
+        "bash" | "sh" | "shell" => "bash",
+        "c" | "h" => "c",
+        "lua" => "lua",
+        "query" | "scheme" => "query",
+        "vim" | "viml" => "vim",
+        _ => return None,
+    };
+    CONFIGS.get(name)
+}
+
+/// Returns the `code` of the `language` as HTML, with highlighted text wrapped in spans like
+/// `<span class="hl-keyword">`. Returns none if the language is not bundled or the code fails to
+/// highlight.
+pub fn highlight(language: &str, code: &str) -> Option<String> {
+    let config = config(language)?;
+
+    HIGHLIGHTER.with(|highlighter| {
+        let mut highlighter = highlighter.borrow_mut();
+        let events = highlighter
+            .highlight(config, code.as_bytes(), None, |_| None)
+            .ok()?;
+
+        let mut html = String::new();
+        for event in events {
+            match event.ok()? {
+                HighlightEvent::Source { start, end } => {
+                    html.push_str(&utils::escape_html(&code[start..end]))
+                }
+                HighlightEvent::HighlightStart(highlight) => {
+                    let name = HIGHLIGHT_NAMES[highlight.0];
+                    let _ = write!(html, r#"<span class="hl-{name}">"#);
+                }
+                HighlightEvent::HighlightEnd => html.push_str("</span>"),
+            }
+        }
+        Some(html)
+    })
+}
+
+#[cfg(all(test, feature = "highlight"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_load_every_bundled_language() {
+        for name in ["bash", "c", "lua", "query", "vim"] {
+            assert!(CONFIGS.contains_key(name), "Failed to load {name}");
+        }
+    }
+
+    #[test]
+    fn should_highlight_snippets() {
+        let snippets = [
+            ("vim", "let g:loaded = 1"),
+            ("lua", "local x = require('vim.lsp')"),
+            ("c", "int main(void) { return 0; }"),
+            ("sh", "echo \"hello\" # greet"),
+            ("query", "(identifier) @variable"),
+        ];
+        for (language, code) in snippets {
+            let html = highlight(language, code)
+                .unwrap_or_else(|| panic!("Failed to highlight {language}"));
+            assert!(html.contains(r#"<span class="hl-"#), "{language}: {html}");
+        }
+    }
+}
@@ -0,0 +1,53 @@
+; Highlights for tree-sitter-bash, limited to nodes shared by its releases.
+
+(comment) @comment
+
+[
+  (string)
+  (raw_string)
+  (heredoc_body)
+] @string
+
+(command_name) @function
+(function_definition name: (word) @function)
+
+[
+  (variable_name)
+  (special_variable_name)
+] @variable
+
+(file_descriptor) @number
+(test_operator) @operator
+
+[
+  "case"
+  "declare"
+  "do"
+  "done"
+  "elif"
+  "else"
+  "esac"
+  "export"
+  "fi"
+  "for"
+  "function"
+  "if"
+  "in"
+  "local"
+  "readonly"
+  "then"
+  "unset"
+  "while"
+] @keyword
+
+[
+  "&&"
+  "||"
+  "|"
+  ">"
+  ">>"
+  "<"
+  "="
+  "=="
+  "!="
+] @operator
@@ -0,0 +1,59 @@
+; Highlights for tree-sitter-lua.
+
+(comment) @comment
+(string) @string
+(number) @number
+
+[
+  (nil)
+  (true)
+  (false)
+] @constant
+
+(function_declaration name: (identifier) @function)
+(function_call name: (identifier) @function)
+(function_call name: (dot_index_expression field: (identifier) @function))
+(function_call name: (method_index_expression method: (identifier) @function))
+(dot_index_expression field: (identifier) @property)
+
+(parameters (identifier) @variable)
+
+[
+  "and"
+  "break"
+  "do"
+  "else"
+  "elseif"
+  "end"
+  "for"
+  "function"
+  "goto"
+  "if"
+  "in"
+  "local"
+  "not"
+  "or"
+  "repeat"
+  "return"
+  "then"
+  "until"
+  "while"
+] @keyword
+
+[
+  "="
+  "=="
+  "~="
+  "<"
+  "<="
+  ">"
+  ">="
+  "+"
+  "-"
+  "*"
+  "/"
+  "%"
+  "^"
+  "#"
+  ".."
+] @operator
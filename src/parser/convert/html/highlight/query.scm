@@ -0,0 +1,9 @@
+; Highlights for tree-sitter-query.
+
+(comment) @comment
+(string) @string
+(capture) @label
+
+(named_node name: (identifier) @type)
+(field_definition name: (identifier) @property)
+(predicate name: (identifier) @function)
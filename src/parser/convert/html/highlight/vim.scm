@@ -0,0 +1,28 @@
+; Highlights for tree-sitter-vim.
+
+(comment) @comment
+(string_literal) @string
+
+[
+  (integer_literal)
+  (float_literal)
+] @number
+
+(call_expression function: (identifier) @function)
+
+[
+  "call"
+  "else"
+  "elseif"
+  "endfor"
+  "endfunction"
+  "endif"
+  "endwhile"
+  "for"
+  "function"
+  "if"
+  "in"
+  "let"
+  "return"
+  "while"
+] @keyword
@@ -0,0 +1,181 @@
+use crate::utils;
+use std::io;
+
+/// Template used by default to produce a standalone HTML document.
+const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+{{head}}
+</head>
+<body>
+<nav class="help-pager">{{prev}}{{next}}</nav>
+{{toc}}
+<main class="help-body">
+{{body}}
+</main>
+</body>
+</html>
+"#;
+
+/// Link to another page, used as the previous or next page of a document.
+#[derive(Clone, Debug)]
+pub struct PageLink {
+    pub href: String,
+    pub title: String,
+}
+
+impl PageLink {
+    /// Creates a link to the page `to_page` from the page `from_page`, where both are
+    /// `/`-separated paths from the same root.
+    pub fn new(from_page: &str, to_page: &str, title: impl Into<String>) -> Self {
+        Self {
+            href: utils::relative_url(from_page, to_page),
+            title: title.into(),
+        }
+    }
+}
+
+/// Options used to wrap HTML into a standalone document.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlDocumentOpt {
+    /// Template used to produce the document.
+    pub template: HtmlTemplate,
+
+    /// Title of the document. If not provided, the tag on the first line of the help file is
+    /// used, e.g. `api.txt` for `*api.txt*  Nvim`.
+    pub title: Option<String>,
+
+    /// Custom HTML included within `<head>`.
+    pub head: String,
+
+    /// Link to the previous page, if any.
+    pub prev: Option<PageLink>,
+
+    /// Link to the next page, if any.
+    pub next: Option<PageLink>,
+}
+
+impl HtmlDocumentOpt {
+    /// Writes a document for the help file `src` to the `writer`, including the `toc` HTML, where
+    /// the body of the document is written by calling `write_body`.
+    pub fn write_to<W: io::Write>(
+        &self,
+        src: &str,
+        toc: &str,
+        writer: &mut W,
+        write_body: impl FnOnce(&mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let title = self
+            .title
+            .as_deref()
+            .or_else(|| utils::first_line_tag(src))
+            .unwrap_or("vimdoc");
+        let prev = self
+            .prev
+            .as_ref()
+            .map(|link| page_link_html(link, "help-prev"))
+            .unwrap_or_default();
+        let next = self
+            .next
+            .as_ref()
+            .map(|link| page_link_html(link, "help-next"))
+            .unwrap_or_default();
+
+        self.template.write_to(
+            writer,
+            &[
+                ("title", &utils::escape_html(title)),
+                ("head", &self.head),
+                ("toc", toc),
+                ("prev", &prev),
+                ("next", &next),
+            ],
+            write_body,
+        )
+    }
+}
+
+/// Returns an `<a>` to the page using the given css class.
+fn page_link_html(link: &PageLink, class: &str) -> String {
+    format!(
+        r#"<a class="{class}" href="{}">{}</a>"#,
+        link.href,
+        utils::escape_html(&link.title)
+    )
+}
+
+/// Template for a standalone HTML document. Placeholders are written as `{{name}}` and support
+/// `title`, `head`, `toc`, `body`, `prev`, and `next`.
+#[derive(Clone, Debug)]
+pub struct HtmlTemplate(String);
+
+impl Default for HtmlTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_TEMPLATE)
+    }
+}
+
+impl HtmlTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self(template.into())
+    }
+
+    /// Writes the template to the `writer`, replacing each `{{name}}` placeholder with the value
+    /// of the same name, except that the first `{{body}}` placeholder is written by calling
+    /// `write_body`. This allows the body to be written directly into the document rather than
+    /// being rendered into memory first. Unknown placeholders are left as-is, and values are never
+    /// themselves searched for placeholders.
+    pub fn write_to<W: io::Write>(
+        &self,
+        writer: &mut W,
+        values: &[(&str, &str)],
+        write_body: impl FnOnce(&mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut write_body = Some(write_body);
+        self.write_with(writer, |writer, name| {
+            if name == "body" {
+                if let Some(write_body) = write_body.take() {
+                    return write_body(writer).map(|_| true);
+                }
+            }
+
+            match values.iter().find(|(key, _)| *key == name) {
+                Some((_, value)) => writer.write_all(value.as_bytes()).map(|_| true),
+                None => Ok(false),
+            }
+        })
+    }
+
+    /// Writes the template to the `writer`, calling `write_value` with the name of each
+    /// placeholder to write its value, which returns false if the placeholder is unknown.
+    fn write_with<W: io::Write>(
+        &self,
+        writer: &mut W,
+        mut write_value: impl FnMut(&mut W, &str) -> io::Result<bool>,
+    ) -> io::Result<()> {
+        let mut remaining = self.0.as_str();
+
+        while let Some(start) = remaining.find("{{") {
+            writer.write_all(&remaining.as_bytes()[..start])?;
+            remaining = &remaining[start..];
+
+            let end = match remaining.find("}}") {
+                Some(end) if write_value(writer, remaining[2..end].trim())? => Some(end),
+                _ => None,
+            };
+
+            match end {
+                Some(end) => remaining = &remaining[end + 2..],
+                None => {
+                    writer.write_all(b"{{")?;
+                    remaining = &remaining[2..];
+                }
+            }
+        }
+
+        writer.write_all(remaining.as_bytes())
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Stylesheet shared by all themes, which references the variables each theme defines.
+const BASE_CSS: &str = include_str!("theme/base.css");
+
+const LIGHT_CSS: &str = include_str!("theme/light.css");
+const DARK_CSS: &str = include_str!("theme/dark.css");
+const TERMINAL_CSS: &str = include_str!("theme/terminal.css");
+
+/// Bundled themes used to style the HTML produced by [`HtmlString`](super::HtmlString).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HtmlTheme {
+    /// Light theme that switches to the dark theme when the browser prefers a dark color scheme.
+    #[default]
+    Default,
+    Light,
+    Dark,
+    /// Dark, monospace theme that resembles reading `:help` within a terminal.
+    Terminal,
+}
+
+impl HtmlTheme {
+    /// Returns the complete stylesheet of the theme.
+    pub fn css(self) -> String {
+        let theme = match self {
+            Self::Default => {
+                format!("{LIGHT_CSS}\n@media (prefers-color-scheme: dark) {{\n{DARK_CSS}}}\n")
+            }
+            Self::Light => LIGHT_CSS.to_string(),
+            Self::Dark => DARK_CSS.to_string(),
+            Self::Terminal => TERMINAL_CSS.to_string(),
+        };
+
+        // Themes come last so they can override rules of the base stylesheet
+        format!("{BASE_CSS}\n{theme}")
+    }
+}
+
+impl fmt::Display for HtmlTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Light => write!(f, "light"),
+            Self::Dark => write!(f, "dark"),
+            Self::Terminal => write!(f, "terminal"),
+        }
+    }
+}
+
+impl FromStr for HtmlTheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            "terminal" => Ok(Self::Terminal),
+            _ => Err(format!(
+                "Unknown theme {s:?}, expected one of default, light, dark, or terminal"
+            )),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::{FromParser, Parser};
+use crate::{Context, Error, Visitor};
+use serde::Serialize;
+use std::fmt;
+use std::ops::Deref;
+
+/// Position within the source, where both row and column start at 0 and the column is in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct JsonPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl From<tree_sitter::Point> for JsonPosition {
+    fn from(point: tree_sitter::Point) -> Self {
+        Self {
+            row: point.row,
+            column: point.column,
+        }
+    }
+}
+
+/// Named node within a [`JsonAst`].
+#[derive(Clone, Debug, Serialize)]
+pub struct JsonNode {
+    /// Kind of the node, being the name of its [`NodeType`](crate::NodeType) or `ERROR`.
+    pub kind: String,
+
+    /// Byte offset where the node starts within the source.
+    pub start_byte: usize,
+
+    /// Byte offset where the node ends (exclusive) within the source.
+    pub end_byte: usize,
+
+    /// Position where the node starts within the source.
+    pub start: JsonPosition,
+
+    /// Position where the node ends within the source.
+    pub end: JsonPosition,
+
+    /// Untruncated text of the node.
+    pub text: String,
+
+    /// True if the node is an error or contains an error.
+    pub error: bool,
+
+    /// Named children of the node.
+    pub children: Vec<JsonNode>,
+}
+
+/// Tree of named nodes from a [`Parser`] that can be serialized as JSON.
+#[derive(Clone, Debug, Serialize)]
+#[serde(transparent)]
+pub struct JsonAst(JsonNode);
+
+impl JsonAst {
+    /// Returns the tree serialized as indented JSON, failing if the tree cannot be serialized.
+    pub fn to_string_pretty(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&self.0)?)
+    }
+}
+
+impl Deref for JsonAst {
+    type Target = JsonNode;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for JsonAst {
+    /// Writes the tree as JSON on a single line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(&self.0).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+impl FromParser for JsonAst {
+    type Err = Error;
+
+    /// Parses into a tree of named nodes.
+    fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
+        struct JsonVisitor;
+
+        impl Visitor for JsonVisitor {
+            type Output = JsonNode;
+
+            fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
+                let node = ctx.node();
+                JsonNode {
+                    kind: node.kind().to_string(),
+                    start_byte: node.start_byte(),
+                    end_byte: node.end_byte(),
+                    start: node.start_position().into(),
+                    end: node.end_position().into(),
+                    text: ctx.node_raw_text().to_string(),
+                    error: node.has_error(),
+                    children: self.visit_children_named(ctx),
+                }
+            }
+        }
+
+        Ok(JsonAst(JsonVisitor.visit(&mut Context::new(
+            parser.src(),
+            &mut parser.tree().walk(),
+        ))))
+    }
+}
@@ -0,0 +1,436 @@
+use super::{ConverterState, FromParser, Parser, VimdocTranslator};
+use crate::utils;
+use crate::{Context, Converter, Error, NodeExt, NodeType, TagIndex, Visitor};
+use std::ops::{Deref, DerefMut};
+
+/// Newtype [`String`] representing Markdown (CommonMark with GitHub extensions) output from a
+/// [`Parser`].
+pub struct MarkdownString(String);
+
+impl From<MarkdownString> for String {
+    fn from(x: MarkdownString) -> Self {
+        x.0
+    }
+}
+
+impl Deref for MarkdownString {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for MarkdownString {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl MarkdownString {
+    /// Parses into a Markdown string using the provided options, failing if the tree contains a
+    /// node that cannot be converted.
+    pub fn from_parser_with_opt(
+        parser: &Parser,
+        opt: MarkdownTranslatorOpt<'_>,
+    ) -> Result<Self, Error> {
+        let mut converter = Converter::new(MarkdownTranslator::new(opt));
+        let markdown = converter.visit(&mut Context::new(parser.src(), &mut parser.tree().walk()));
+
+        match converter.take_error() {
+            Some(x) => Err(x),
+            None => Ok(MarkdownString(format!("{}\n", markdown.trim_end()))),
+        }
+    }
+}
+
+impl FromParser for MarkdownString {
+    type Err = Error;
+
+    /// Parses into a Markdown string.
+    fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
+        Self::from_parser_with_opt(parser, MarkdownTranslatorOpt::default())
+    }
+}
+
+/// Options for the Markdown translator.
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownTranslatorOpt<'a> {
+    /// Index of tags across all pages, used to resolve taglinks to other pages. If not provided,
+    /// all taglinks are assumed to refer to the current page.
+    pub tags: Option<&'a TagIndex>,
+
+    /// Page being generated as a `/`-separated path, used to produce relative links to other
+    /// pages in [`TagIndex`].
+    pub page: Option<String>,
+}
+
+/// Implementation of [`VimdocTranslator`] that produces Markdown.
+pub struct MarkdownTranslator<'a> {
+    opt: MarkdownTranslatorOpt<'a>,
+}
+
+impl<'a> MarkdownTranslator<'a> {
+    pub fn new(opt: MarkdownTranslatorOpt<'a>) -> Self {
+        Self { opt }
+    }
+
+    /// Returns the heading for the node being visited, preceded by an explicit anchor if the
+    /// heading does not contain a tag.
+    fn heading(
+        &self,
+        ctx: &Context<'_, '_, '_>,
+        state: &ConverterState,
+        level: usize,
+        text: String,
+    ) -> String {
+        let node = ctx.node();
+        let mut cursor = node.walk();
+        let has_tag = node
+            .named_children(&mut cursor)
+            .any(|node| matches!(node.node_type(), Some(NodeType::Tag)));
+
+        let anchor = match state.toc.last() {
+            Some(heading) if !has_tag => {
+                format!("<a name=\"{}\"></a>\n", utils::url_encode(&heading.tag))
+            }
+            _ => String::new(),
+        };
+
+        format!("\n{anchor}{} {}\n\n", "#".repeat(level), text.trim())
+    }
+
+    /// Returns a link to the tag, displaying the tag itself.
+    fn link(&self, tagname: &str) -> String {
+        let href = self
+            .opt
+            .tags
+            .and_then(|tags| tags.href(tagname, self.opt.page.as_deref()))
+            .unwrap_or_else(|| format!("#{}", utils::url_encode(tagname)));
+        format!("[{}]({href})", escape_markdown(tagname))
+    }
+}
+
+impl VimdocTranslator for MarkdownTranslator<'_> {
+    type Output = String;
+
+    /// Joins outputs with a space, except after an output that ends a line.
+    fn join(&self, outputs: Vec<Self::Output>) -> Self::Output {
+        let mut joined = String::new();
+        for output in outputs.into_iter().filter(|output| !output.is_empty()) {
+            if !joined.is_empty() && !joined.ends_with('\n') {
+                joined.push(' ');
+            }
+            joined.push_str(&output);
+        }
+        joined
+    }
+
+    fn separator(&self, prev: Option<&Self::Output>, next: &Self::Output) -> &'static str {
+        match prev {
+            Some(prev) if !prev.is_empty() && !prev.ends_with('\n') && !next.is_empty() => " ",
+            _ => "",
+        }
+    }
+
+    fn escape(&self, text: &str) -> Self::Output {
+        escape_markdown(text)
+    }
+
+    fn argument<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        code_span(ctx.node_raw_text().trim())
+    }
+
+    fn block<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        format!("{}\n\n", text.trim_end())
+    }
+
+    fn code<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        // Code is taken from the source as-is since Markdown does not escape within fences
+        let code = utils::trim_indent(ctx.node_raw_text(), /* tab=8space */ 8);
+        let language = state.language.as_deref().unwrap_or_default();
+
+        // The fence must be longer than any fence within the code so the code cannot end it
+        let fence = "`".repeat(longest_backtick_run(&code).max(2) + 1);
+        format!("{fence}{language}\n{}\n{fence}\n", code.trim_end())
+    }
+
+    fn codeblock<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        // Codeblocks begin at the end of a line, but fences must start on their own line
+        format!("\n{text}")
+    }
+
+    fn codespan<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        code_span(ctx.node_raw_text().trim().trim_matches('`'))
+    }
+
+    fn column_heading<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        format!("**{}**\n", text.trim())
+    }
+
+    fn h1<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        self.heading(ctx, state, 1, text)
+    }
+
+    fn h2<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        self.heading(ctx, state, 2, text)
+    }
+
+    fn h3<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        self.heading(ctx, state, 3, text)
+    }
+
+    fn help_file<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
+    }
+
+    fn keycode<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        code_span(ctx.node_raw_text().trim())
+    }
+
+    fn language<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        String::new()
+    }
+
+    fn line<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        let text = escape_line_start(&text);
+        if text.ends_with('\n') {
+            text
+        } else {
+            format!("{text}\n")
+        }
+    }
+
+    fn line_li<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        let indent = "  ".repeat(state.indent.saturating_sub(1));
+        format!("{indent}- {}\n", escape_line_start(text.trim()))
+    }
+
+    fn optionlink<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        // Optionlinks keep their quotes as part of the tag, e.g. 'tabstop'
+        self.link(ctx.node_raw_text().trim())
+    }
+
+    fn tag<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        let tagname = ctx.node_raw_text().trim().trim_matches('*');
+        let anchor = format!(r#"<a name="{}"></a>"#, utils::url_encode(tagname));
+
+        // Headings display their own text, so tags within them only provide anchors
+        if matches!(
+            ctx.parent_node_type(),
+            Some(NodeType::H1 | NodeType::H2 | NodeType::H3)
+        ) {
+            anchor
+        } else {
+            format!("{anchor}{}", code_span(tagname))
+        }
+    }
+
+    fn taglink<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        self.link(ctx.node_raw_text().trim().trim_matches('|'))
+    }
+
+    fn uppercase_name<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
+    }
+
+    fn url<'src, 'tree>(
+        &mut self,
+        ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        _text: Self::Output,
+    ) -> Self::Output {
+        let (href, remaining) = utils::fix_url(ctx.node_raw_text().trim());
+        format!("<{href}>{}", escape_markdown(remaining))
+    }
+
+    fn word<'src, 'tree>(
+        &mut self,
+        _ctx: &mut Context<'src, 'tree, '_>,
+        _state: &ConverterState,
+        text: Self::Output,
+    ) -> Self::Output {
+        text
+    }
+}
+
+/// Escapes characters that would otherwise be treated as Markdown syntax.
+fn escape_markdown(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '&' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes a marker at the start of a line that would otherwise begin a block, such as the `#` of
+/// a heading, the `-` or `+` of a list item, or the `1.` of an ordered list item. Other markers
+/// like `*` and `>` are escaped wherever they appear by [`escape_markdown`].
+fn escape_line_start(s: &str) -> String {
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match s[digits..].chars().next() {
+        Some('.' | ')') if digits > 0 => Some(digits),
+        Some('#' | '-' | '+' | '=') if digits == 0 => Some(0),
+        _ => None,
+    };
+
+    match marker {
+        Some(i) => format!("{}\\{}", &s[..i], &s[i..]),
+        None => s.to_string(),
+    }
+}
+
+/// Returns the length of the longest run of backticks within the str.
+fn longest_backtick_run(s: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in s.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+/// Wraps the str in a code span, using enough backticks to contain any backticks within.
+fn code_span(s: &str) -> String {
+    let longest = longest_backtick_run(s);
+    let fence = "`".repeat(longest + 1);
+    if longest > 0 {
+        format!("{fence} {s} {fence}")
+    } else {
+        format!("{fence}{s}{fence}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_escape_markers_that_start_blocks() {
+        assert_eq!(escape_line_start("# not a heading"), r"\# not a heading");
+        assert_eq!(escape_line_start("- not a list"), r"\- not a list");
+        assert_eq!(escape_line_start("+ not a list"), r"\+ not a list");
+        assert_eq!(escape_line_start("1. not a list"), r"1\. not a list");
+        assert_eq!(escape_line_start("10) not a list"), r"10\) not a list");
+        assert_eq!(escape_line_start("2023 was a year"), "2023 was a year");
+        assert_eq!(escape_line_start("a - b # c"), "a - b # c");
+    }
+
+    #[test]
+    fn should_escape_inline_syntax() {
+        assert_eq!(escape_markdown("a & b"), r"a \& b");
+        assert_eq!(escape_markdown("&amp;"), r"\&amp;");
+        assert_eq!(escape_markdown("a | b"), r"a \| b");
+        assert_eq!(escape_markdown("> quote"), r"\> quote");
+        assert_eq!(escape_markdown("* item"), r"\* item");
+    }
+
+    #[test]
+    fn should_use_fences_longer_than_backticks_within() {
+        assert_eq!(longest_backtick_run("no backticks"), 0);
+        assert_eq!(longest_backtick_run("``` and ````"), 4);
+        assert_eq!(code_span("a"), "`a`");
+        assert_eq!(code_span("a`b"), "`` a`b ``");
+    }
+}
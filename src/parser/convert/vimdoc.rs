@@ -0,0 +1,228 @@
+use super::{FromParser, Parser};
+use crate::utils;
+use crate::{Context, Error, NodeExt, NodeType, Visitor, UNIT_JOINER};
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+
+/// Column that right-aligned tags and separator lines extend to.
+const TEXTWIDTH: usize = 78;
+
+/// Newtype [`String`] representing canonical vimdoc output from a [`Parser`].
+///
+/// The source is reproduced line-by-line with the following normalizations:
+///
+/// * Trailing whitespace is removed from every line.
+/// * Separator lines of headings are extended to `=====`/`-----` of [`TEXTWIDTH`] characters.
+/// * Tags at the end of a line that are set apart from the text before them are right-aligned
+///   to end at [`TEXTWIDTH`].
+/// * Code blocks start with a single space before `>` (or `>lang`), and a line holding only the
+///   `<` that ends a code block has the whitespace around it removed. Text following the `<` on
+///   the same line is left as is.
+///
+/// Formatting output that is already canonical produces the same output.
+pub struct VimdocString(String);
+
+impl From<VimdocString> for String {
+    fn from(x: VimdocString) -> Self {
+        x.0
+    }
+}
+
+impl Deref for VimdocString {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for VimdocString {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Normalization to apply to a single row of the source.
+enum RowEdit {
+    /// Replace the row with a separator made of the character.
+    Separator(char),
+
+    /// Row starts a code block with `>` at the byte column.
+    CodeStart(usize),
+}
+
+/// Collects the rows of the source to normalize.
+#[derive(Default)]
+struct RowVisitor {
+    edits: HashMap<usize, RowEdit>,
+
+    /// Byte ranges of tags within each row.
+    tags: HashMap<usize, Vec<(usize, usize)>>,
+
+    /// Rows with a `<` token.
+    code_ends: HashSet<usize>,
+}
+
+impl Visitor for RowVisitor {
+    type Output = ();
+
+    fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
+        let node = ctx.node();
+        let start = node.start_position();
+
+        match node.node_type() {
+            Some(NodeType::H1) => {
+                self.edits.insert(start.row, RowEdit::Separator('='));
+            }
+            Some(NodeType::H2) => {
+                self.edits.insert(start.row, RowEdit::Separator('-'));
+            }
+            Some(NodeType::Tag) if !node.has_error() => {
+                let end = node.end_position();
+                if end.row == start.row {
+                    self.tags
+                        .entry(start.row)
+                        .or_default()
+                        .push((start.column, end.column));
+                }
+            }
+            Some(NodeType::Codeblock) => {
+                self.edits
+                    .insert(start.row, RowEdit::CodeStart(start.column));
+            }
+            None if !node.is_named() && node.kind() == "<" => {
+                self.code_ends.insert(start.row);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl FromParser for VimdocString {
+    type Err = Error;
+
+    /// Parses into canonical vimdoc.
+    fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
+        let mut visitor = RowVisitor::default();
+        visitor.visit_all(
+            &mut Context::new(parser.src(), &mut parser.tree().walk()),
+            &UNIT_JOINER,
+            /* unnamed */ true,
+        );
+
+        let mut out = String::with_capacity(parser.src().len());
+        for (row, line) in parser.src().lines().enumerate() {
+            let formatted = match visitor.edits.get(&row) {
+                Some(RowEdit::Separator(c)) if line.trim().chars().all(|x| x == *c) => {
+                    c.to_string().repeat(TEXTWIDTH)
+                }
+                Some(RowEdit::CodeStart(col)) if line.is_char_boundary(*col) => {
+                    let left = line[..*col].trim_end();
+                    let marker = line[*col..].trim();
+                    if left.is_empty() {
+                        marker.to_string()
+                    } else {
+                        format!("{left} {marker}")
+                    }
+                }
+                _ if visitor.code_ends.contains(&row) && line.trim() == "<" => String::from("<"),
+                _ => match visitor.tags.get_mut(&row) {
+                    Some(tags) => {
+                        tags.sort_unstable();
+                        align_tags(line, tags)
+                    }
+                    None => line.trim_end().to_string(),
+                },
+            };
+
+            out.push_str(&formatted);
+            out.push('\n');
+        }
+
+        Ok(VimdocString(out))
+    }
+}
+
+/// Right-aligns the tags at the end of the `line` to end at [`TEXTWIDTH`], where `tags` are the
+/// sorted byte ranges of each tag within the line. Tags are only aligned when the line consists
+/// solely of tags or the tags are set apart from the text before them by a tab or multiple
+/// spaces; otherwise, the line is returned without trailing whitespace.
+fn align_tags(line: &str, tags: &[(usize, usize)]) -> String {
+    // Find the first tag of the run of tags that ends the line
+    let mut first = tags.len();
+    let mut next_start = line.len();
+    for (i, (start, end)) in tags.iter().enumerate().rev() {
+        if line
+            .get(*end..next_start)
+            .map_or(true, |s| !s.trim().is_empty())
+        {
+            break;
+        }
+        first = i;
+        next_start = *start;
+    }
+
+    let Some(&(run_start, _)) = tags.get(first) else {
+        return line.trim_end().to_string();
+    };
+
+    let left = line[..run_start].trim_end();
+    let gap = &line[left.len()..run_start];
+    if !left.trim().is_empty() && !gap.contains('\t') && gap.len() < 2 {
+        return line.trim_end().to_string();
+    }
+
+    let right = tags[first..]
+        .iter()
+        .map(|(start, end)| &line[*start..*end])
+        .collect::<Vec<_>>()
+        .join(" ");
+    let left = if left.trim().is_empty() { "" } else { left };
+    let used = utils::display_width(left) + utils::display_width(&right);
+
+    match TEXTWIDTH.checked_sub(used) {
+        Some(pad) if left.is_empty() || pad > 0 => format!("{left}{}{right}", " ".repeat(pad)),
+        _ if left.is_empty() => right,
+        _ => format!("{left} {right}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "*fixture.txt*\tFormatting fixture\t
+
+==========
+INTRODUCTION\t\t\t\t\t*fixture-intro*
+
+Text with a tag set apart from it.  *fixture-tag*
+Text with a tag set apart by a tab.\t*fixture-tab* *fixture-tab2*
+
+Example:    >vim
+    echo 'hello'
+  <
+Text after the example.\t
+
+Another example: >
+    echo 'world'
+<\tText following the end of the example.
+
+------
+SECTION\t*fixture-section*
+
+ vim:tw=78:ts=8:ft=help:norl:
+";
+
+    fn fmt(src: &str) -> String {
+        let parser = Parser::load_vimdoc(src.as_bytes()).unwrap();
+        parser.parse::<VimdocString>().unwrap().into()
+    }
+
+    #[test]
+    fn should_produce_same_output_when_formatted_twice() {
+        let once = fmt(FIXTURE);
+        assert_ne!(once, FIXTURE, "Fixture should need formatting");
+        assert_eq!(fmt(&once), once);
+    }
+}
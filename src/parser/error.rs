@@ -0,0 +1,135 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors that can occur when loading, parsing, or converting vimdoc.
+///
+/// Errors are produced without knowing the file being processed, so callers attach the path of
+/// the file using [`Error::with_path`].
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to read vimdoc or write its output.
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+
+    /// Failed to load the tree-sitter language used to parse vimdoc.
+    Language {
+        path: Option<PathBuf>,
+        source: tree_sitter::LanguageError,
+    },
+
+    /// Tree-sitter failed to produce a tree for the vimdoc.
+    Parse { path: Option<PathBuf> },
+
+    /// Failed to serialize output as JSON.
+    Json {
+        path: Option<PathBuf>,
+        source: serde_json::Error,
+    },
+
+    /// Encountered a `line` within a `tags` file that is not a valid entry, where `row` is 1-based.
+    TagsFile {
+        path: Option<PathBuf>,
+        row: usize,
+        line: String,
+    },
+
+    /// Encountered a node in the tree that cannot be converted, which happens when the grammar
+    /// produces nodes newer than those known by [`NodeType`](crate::NodeType).
+    UnsupportedNode {
+        path: Option<PathBuf>,
+        kind: String,
+        position: tree_sitter::Point,
+    },
+}
+
+impl Error {
+    /// Attaches the `path` of the file being processed to the error.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = Some(path.into());
+        match &mut self {
+            Self::Io { path: p, .. }
+            | Self::Language { path: p, .. }
+            | Self::Parse { path: p }
+            | Self::Json { path: p, .. }
+            | Self::TagsFile { path: p, .. }
+            | Self::UnsupportedNode { path: p, .. } => *p = path,
+        }
+        self
+    }
+
+    /// Returns the path of the file being processed, if known.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Io { path, .. }
+            | Self::Language { path, .. }
+            | Self::Parse { path }
+            | Self::Json { path, .. }
+            | Self::TagsFile { path, .. }
+            | Self::UnsupportedNode { path, .. } => path.as_deref(),
+        }
+    }
+
+    /// Returns the position within the file that caused the error, if known.
+    pub fn position(&self) -> Option<tree_sitter::Point> {
+        match self {
+            Self::UnsupportedNode { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Errors are prefixed with their location like `path:line:col: ` when known
+        if let Some(path) = self.path() {
+            write!(f, "{}:", path.display())?;
+            if let Some(position) = self.position() {
+                write!(f, "{}:{}:", position.row + 1, position.column + 1)?;
+            }
+            write!(f, " ")?;
+        }
+
+        match self {
+            Self::Io { source, .. } => write!(f, "{source}"),
+            Self::Language { source, .. } => write!(f, "Failed to load language: {source}"),
+            Self::Parse { .. } => write!(f, "Failed to parse vimdoc"),
+            Self::Json { source, .. } => write!(f, "Failed to serialize JSON: {source}"),
+            Self::TagsFile { row, line, .. } => {
+                write!(f, "Invalid tags entry on line {row}: {line:?}")
+            }
+            Self::UnsupportedNode { kind, .. } => write!(f, "Unsupported node {kind:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Language { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Self::Io { path: None, source }
+    }
+}
+
+impl From<tree_sitter::LanguageError> for Error {
+    fn from(source: tree_sitter::LanguageError) -> Self {
+        Self::Language { path: None, source }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(source: serde_json::Error) -> Self {
+        Self::Json { path: None, source }
+    }
+}
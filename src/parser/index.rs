@@ -0,0 +1,136 @@
+use crate::utils;
+use crate::{Context, NodeType, Parser, TagsFile, Visitor, UNIT_JOINER};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Location of a tag across a collection of help files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagLocation {
+    /// Page containing the tag, as a `/`-separated path such as `doc/api.html`.
+    pub page: String,
+
+    /// Url-encoded anchor of the tag within the page.
+    pub anchor: String,
+}
+
+impl TagLocation {
+    /// Returns the href to this tag when linked from `from_page`.
+    pub fn href(&self, from_page: Option<&str>) -> String {
+        match from_page {
+            Some(from_page) => format!(
+                "{}#{}",
+                utils::relative_url(from_page, &self.page),
+                self.anchor
+            ),
+            None => format!("{}#{}", self.page, self.anchor),
+        }
+    }
+}
+
+/// Index of every tag across a collection of help files, used to resolve taglinks to the page
+/// that defines them. Tags from external documentation can also be indexed, which are used when
+/// a tag is not found within the local help files.
+#[derive(Clone, Debug, Default)]
+pub struct TagIndex {
+    tags: HashMap<String, TagLocation>,
+    external: HashMap<String, String>,
+}
+
+impl TagIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds every tag found by the `parser` to the index as belonging to `page`. If a tag is
+    /// already indexed, the original location is kept.
+    pub fn insert_from_parser(&mut self, parser: &Parser, page: &str) {
+        for tag in collect_tags(parser) {
+            let anchor = utils::url_encode(&tag);
+            self.tags.entry(tag).or_insert_with(|| TagLocation {
+                page: page.to_string(),
+                anchor,
+            });
+        }
+    }
+
+    /// Adds every entry of the `tags_file` to the index as external documentation, linking to the
+    /// url produced by `url_template`. Within the template, `{file}` is replaced with the url-encoded
+    /// name of the help file without its extension and `{tag}` is replaced with the url-encoded tag,
+    /// e.g. `https://neovim.io/doc/user/{file}.html#{tag}`. If a tag is already indexed externally,
+    /// the original url is kept.
+    pub fn insert_from_tags_file(&mut self, tags_file: &TagsFile, url_template: &str) {
+        for entry in tags_file.entries.iter() {
+            let file = Path::new(&entry.file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy())
+                .unwrap_or_default();
+            let url = url_template
+                .replace("{file}", &utils::url_encode(&file))
+                .replace("{tag}", &utils::url_encode(&entry.tag));
+            self.external.entry(entry.tag.clone()).or_insert(url);
+        }
+    }
+
+    /// Returns the href to the `tag` when linked from `from_page`, preferring local help files
+    /// over external documentation.
+    pub fn href(&self, tag: &str, from_page: Option<&str>) -> Option<String> {
+        match self.tags.get(tag) {
+            Some(location) => Some(location.href(from_page)),
+            None => self.external.get(tag).cloned(),
+        }
+    }
+
+    /// Returns the location of the `tag` within the local help files, if it is indexed.
+    pub fn get(&self, tag: &str) -> Option<&TagLocation> {
+        self.tags.get(tag)
+    }
+
+    /// Returns the total tags indexed, both local and external.
+    pub fn len(&self) -> usize {
+        self.tags.len() + self.external.len()
+    }
+
+    /// Returns true if no tags are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.external.is_empty()
+    }
+}
+
+/// Returns the names of every `*tag*` within the tree of the `parser`, in order of appearance.
+pub fn collect_tags(parser: &Parser) -> Vec<String> {
+    struct TagCollector(Vec<String>);
+
+    impl Visitor for TagCollector {
+        type Output = ();
+
+        fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
+            if matches!(ctx.node_type(), Some(NodeType::Tag)) && !ctx.has_error() {
+                let tagname = ctx.node_raw_text().trim().trim_matches('*');
+                self.0.push(tagname.to_string());
+            }
+        }
+    }
+
+    let mut collector = TagCollector(Vec::new());
+    collector.visit_all_named(
+        &mut Context::new(parser.src(), &mut parser.tree().walk()),
+        &UNIT_JOINER,
+    );
+    collector.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_encode_external_tags_and_files() {
+        let tags_file: TagsFile = "a\"<b>&c\tfoo&\"bar.txt\t/*a\"<b>&c*\n".parse().unwrap();
+        let mut index = TagIndex::new();
+        index.insert_from_tags_file(&tags_file, "https://example.com/{file}.html#{tag}");
+        assert_eq!(
+            index.href("a\"<b>&c", None).as_deref(),
+            Some("https://example.com/foo%26%22bar.html#a%22%3Cb%3E%26c")
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Keys named in full after modifiers written like `CTRL-`, e.g. `CTRL-PageUp`.
+const NAMED_KEYS: &[&str] = &[
+    "Break", "PageUp", "PageDown", "Insert", "Del", "Home", "End",
+];
+
+/// Modifier held down while pressing a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    /// Alt or meta key, written as `<M-x>`, `<A-x>`, `META-x`, or `ALT-x`.
+    Alt,
+    /// Meta key when it differs from alt, written as `<T-x>`.
+    Meta,
+    /// Command key on macOS, written as `<D-x>`.
+    Cmd,
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ctrl => write!(f, "Ctrl"),
+            Self::Shift => write!(f, "Shift"),
+            Self::Alt => write!(f, "Alt"),
+            Self::Meta => write!(f, "Meta"),
+            Self::Cmd => write!(f, "Cmd"),
+        }
+    }
+}
+
+/// Key pressed along with the modifiers held down, e.g. `<C-w>` is W pressed with Ctrl.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    /// Modifiers held down, in the order they were written.
+    pub modifiers: Vec<Modifier>,
+
+    /// Readable name of the key, e.g. `Enter` for `<CR>`.
+    pub name: String,
+
+    /// Vim notation of the key as written, e.g. `<C-w>` or `CTRL-W`.
+    pub notation: String,
+}
+
+impl fmt::Display for Key {
+    /// Writes the key in readable form, e.g. `Ctrl+W`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in self.modifiers.iter() {
+            write!(f, "{modifier}+")?;
+        }
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Sequence of keys written as a keycode, e.g. `CTRL-W_CTRL-V` is Ctrl+W followed by Ctrl+V.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keycode {
+    pub keys: Vec<Key>,
+}
+
+impl fmt::Display for Keycode {
+    /// Writes the keys in readable form, e.g. `Ctrl+W, Ctrl+V`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Keycode {
+    type Err = ();
+
+    /// Parses keys written in Vim notation like `<C-w>`, `<S-Tab>`, `<Leader>`, `CTRL-W`, and
+    /// `META-x`, where keys of a sequence are separated by `_` or a space, or follow each other
+    /// like `<C-w><C-v>`. Keys after the first can also be a plain character, e.g. `CTRL-W_x`.
+    /// Fails if any key is not recognized, such as the `<buffer>` argument of a mapping.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = Vec::new();
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let (key, remaining) = match parse_key(rest) {
+                Some(parsed) => parsed,
+                None if !keys.is_empty() => parse_char(rest).ok_or(())?,
+                None => return Err(()),
+            };
+            keys.push(key);
+
+            rest = match remaining.strip_prefix(['_', ' ']) {
+                Some(next) if !next.is_empty() => next,
+                _ if remaining.is_empty() || remaining.starts_with('<') => remaining,
+                _ => return Err(()),
+            };
+        }
+
+        if keys.is_empty() {
+            return Err(());
+        }
+        Ok(Self { keys })
+    }
+}
+
+/// Parses the key at the start of `s`, returning it along with the text that follows it.
+fn parse_key(s: &str) -> Option<(Key, &str)> {
+    if let Some(inner) = s.strip_prefix('<') {
+        // The key itself can be `-` or `>`, e.g. `<C-->`, so modifiers are stripped first
+        let mut modifiers = Vec::new();
+        let mut inner = inner;
+        while let Some((modifier, after)) = inner.split_once('-') {
+            let modifier = match modifier {
+                "C" | "c" => Modifier::Ctrl,
+                "S" | "s" => Modifier::Shift,
+                "M" | "m" | "A" | "a" => Modifier::Alt,
+                "T" | "t" => Modifier::Meta,
+                "D" | "d" => Modifier::Cmd,
+                _ => break,
+            };
+            if !after.starts_with('>') || after.starts_with(">>") {
+                modifiers.push(modifier);
+                inner = after;
+            } else {
+                break;
+            }
+        }
+
+        let end = inner.char_indices().skip(1).find(|(_, c)| *c == '>')?.0;
+        let name = key_name(&inner[..end], &modifiers)?;
+        let len = s.len() - inner.len() + end + 1;
+        let notation = s[..len].to_string();
+        return Some((
+            Key {
+                modifiers,
+                name,
+                notation,
+            },
+            &s[len..],
+        ));
+    }
+
+    // Otherwise the key is written like `CTRL-W`, `CTRL-SHIFT-x`, `META-x`, or `CTRL-{char}`
+    let mut modifiers = Vec::new();
+    let mut rest = s;
+    loop {
+        let (modifier, after) = if let Some(after) = rest.strip_prefix("CTRL-") {
+            (Modifier::Ctrl, after)
+        } else if let Some(after) = rest.strip_prefix("SHIFT-") {
+            (Modifier::Shift, after)
+        } else if let Some(after) = rest
+            .strip_prefix("META-")
+            .or_else(|| rest.strip_prefix("ALT-"))
+        {
+            (Modifier::Alt, after)
+        } else {
+            break;
+        };
+        modifiers.push(modifier);
+        rest = after;
+    }
+    if modifiers.is_empty() {
+        return None;
+    }
+
+    let len = if rest.starts_with('{') {
+        rest.find('}')? + 1
+    } else {
+        // Named keys like `CTRL-Break` are preferred over the single character that starts them
+        NAMED_KEYS
+            .iter()
+            .find(|name| rest.starts_with(*name))
+            .map(|name| name.len())
+            .or_else(|| rest.chars().next().map(char::len_utf8))?
+    };
+    let name = match &rest[..len] {
+        key if key.starts_with('{') => key.to_string(),
+        key => key_name(key, &modifiers)?,
+    };
+    let notation_len = s.len() - rest.len() + len;
+    Some((
+        Key {
+            modifiers,
+            name,
+            notation: s[..notation_len].to_string(),
+        },
+        &rest[len..],
+    ))
+}
+
+/// Parses a plain character at the start of `s` as a key pressed without modifiers, as long as it
+/// is the last key or is followed by a separator.
+fn parse_char(s: &str) -> Option<(Key, &str)> {
+    let c = s.chars().next()?;
+    let rest = &s[c.len_utf8()..];
+    if !(rest.is_empty() || rest.starts_with(['_', ' '])) {
+        return None;
+    }
+
+    Some((
+        Key {
+            modifiers: Vec::new(),
+            name: c.to_string(),
+            notation: c.to_string(),
+        },
+        rest,
+    ))
+}
+
+/// Returns the readable name of a key written in Vim notation, or none if it is not a key.
+/// Letters are uppercase when pressed with Ctrl, which ignores case.
+fn key_name(key: &str, modifiers: &[Modifier]) -> Option<String> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(if modifiers.contains(&Modifier::Ctrl) {
+            c.to_ascii_uppercase().to_string()
+        } else {
+            c.to_string()
+        });
+    }
+
+    let lower = key.to_ascii_lowercase();
+    let name = match lower.as_str() {
+        "cr" | "return" | "enter" => "Enter",
+        "nl" | "lf" | "linefeed" => "Newline",
+        "esc" => "Esc",
+        "tab" => "Tab",
+        "bs" | "backspace" => "Backspace",
+        "del" | "delete" => "Delete",
+        "space" => "Space",
+        "insert" => "Insert",
+        "home" => "Home",
+        "end" => "End",
+        "pageup" => "PageUp",
+        "pagedown" => "PageDown",
+        "up" => "Up",
+        "down" => "Down",
+        "left" => "Left",
+        "right" => "Right",
+        "break" => "Break",
+        "help" => "Help",
+        "undo" => "Undo",
+        "leader" => "Leader",
+        "localleader" => "LocalLeader",
+        "lt" => "<",
+        "bar" => "|",
+        "bslash" => "\\",
+        "nul" => "Nul",
+        _ => {
+            // Function keys like `<F1>` through `<F37>`
+            let n = lower.strip_prefix('f')?.parse::<u8>().ok()?;
+            return (1..=37).contains(&n).then(|| format!("F{n}"));
+        }
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the `keycode`, returning the notation and readable form of each of its keys.
+    fn keys(keycode: &str) -> Vec<(String, String)> {
+        let keycode: Keycode = keycode
+            .parse()
+            .unwrap_or_else(|_| panic!("Failed to parse {keycode:?}"));
+        keycode
+            .keys
+            .iter()
+            .map(|key| (key.notation.clone(), key.to_string()))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(notation, readable)| (notation.to_string(), readable.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn should_parse_angle_bracket_notation() {
+        assert_eq!(keys("<C-w>"), pairs(&[("<C-w>", "Ctrl+W")]));
+        assert_eq!(keys("<S-Tab>"), pairs(&[("<S-Tab>", "Shift+Tab")]));
+        assert_eq!(keys("<CR>"), pairs(&[("<CR>", "Enter")]));
+        assert_eq!(keys("<F12>"), pairs(&[("<F12>", "F12")]));
+    }
+
+    #[test]
+    fn should_parse_minus_and_greater_than_as_keys() {
+        assert_eq!(keys("<C-->"), pairs(&[("<C-->", "Ctrl+-")]));
+        assert_eq!(keys("<C->>"), pairs(&[("<C->>", "Ctrl+>")]));
+    }
+
+    #[test]
+    fn should_parse_sequences() {
+        assert_eq!(
+            keys("CTRL-W_CTRL-V"),
+            pairs(&[("CTRL-W", "Ctrl+W"), ("CTRL-V", "Ctrl+V")])
+        );
+        assert_eq!(
+            keys("<C-w><C-v>"),
+            pairs(&[("<C-w>", "Ctrl+W"), ("<C-v>", "Ctrl+V")])
+        );
+        assert_eq!(keys("CTRL-W_x"), pairs(&[("CTRL-W", "Ctrl+W"), ("x", "x")]));
+        assert_eq!(
+            "CTRL-W_CTRL-V".parse::<Keycode>().unwrap().to_string(),
+            "Ctrl+W, Ctrl+V"
+        );
+    }
+
+    #[test]
+    fn should_parse_named_keys_after_modifiers() {
+        assert_eq!(
+            keys("CTRL-PageUp"),
+            pairs(&[("CTRL-PageUp", "Ctrl+PageUp")])
+        );
+        assert_eq!(keys("META-x"), pairs(&[("META-x", "Alt+x")]));
+        assert_eq!(
+            keys("CTRL-{char}"),
+            pairs(&[("CTRL-{char}", "Ctrl+{char}")])
+        );
+    }
+
+    #[test]
+    fn should_reject_text_that_is_not_a_key() {
+        for text in ["<foo>", "<buffer>", "", "CTRL-", "<C-w>x", "foo"] {
+            assert!(
+                text.parse::<Keycode>().is_err(),
+                "{text:?} should not parse"
+            );
+        }
+    }
+}
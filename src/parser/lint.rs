@@ -0,0 +1,370 @@
+use crate::utils;
+use crate::{Context, NodeExt, NodeType, Parser, TagIndex, Visitor, UNIT_JOINER};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Textwidth that lines are checked against when the modeline does not set one.
+const DEFAULT_TEXTWIDTH: usize = 78;
+
+/// Kind of problem reported by the [`Linter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// `|taglink|` that does not match any `*tag*`.
+    UnresolvedTaglink,
+    /// `*tag*` defined more than once.
+    DuplicateTag,
+    /// Line longer than the textwidth of the modeline.
+    LineTooLong,
+    /// Help file without a modeline.
+    MissingModeline,
+    /// Heading without a `*tag*` to link to it.
+    UntaggedHeading,
+    /// Code block that runs to the end of the file without being ended by `<` or a line that is
+    /// not indented.
+    UnclosedCodeblock,
+    /// Text that could not be parsed.
+    ParseError,
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::UnresolvedTaglink => "unresolved-taglink",
+                Self::DuplicateTag => "duplicate-tag",
+                Self::LineTooLong => "line-too-long",
+                Self::MissingModeline => "missing-modeline",
+                Self::UntaggedHeading => "untagged-heading",
+                Self::UnclosedCodeblock => "unclosed-codeblock",
+                Self::ParseError => "parse-error",
+            }
+        )
+    }
+}
+
+/// Problem found within a help file, located by 1-based line and display column, where tabs
+/// expand to the next multiple of 8 columns.
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub rule: LintRule,
+    pub message: String,
+
+    /// Position of the problem as a row and byte column, used to locate it within the source.
+    #[serde(skip)]
+    pub point: tree_sitter::Point,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic at the `point` within the `line` of the source that contains it.
+    fn new(
+        file: &str,
+        line: &str,
+        point: tree_sitter::Point,
+        rule: LintRule,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            file: file.to_string(),
+            line: point.row + 1,
+            column: utils::display_width(utils::truncate_str(line, point.column)) + 1,
+            rule,
+            message: message.into(),
+            point,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file, self.line, self.column, self.rule, self.message
+        )
+    }
+}
+
+/// Checks help files for problems, including those that span multiple files such as taglinks to
+/// tags of another file.
+///
+/// Taglinks that are known false positives, see [`utils::ignore_invalid`], are not reported.
+#[derive(Default)]
+pub struct Linter<'a> {
+    /// Tags of external documentation that taglinks can also resolve to.
+    index: Option<&'a TagIndex>,
+
+    /// First definition of each tag across all help files, as the file and row of the tag.
+    tags: HashMap<String, (String, usize)>,
+
+    /// Taglinks across all help files along with the diagnostic reported if they do not resolve,
+    /// resolved once every file is inserted.
+    taglinks: Vec<(String, Diagnostic)>,
+
+    /// Order that help files were inserted, used to sort diagnostics.
+    files: Vec<String>,
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a linter whose taglinks can also resolve to tags within the `index`.
+    pub fn with_index(index: &'a TagIndex) -> Self {
+        Self {
+            index: Some(index),
+            ..Default::default()
+        }
+    }
+
+    /// Records a `tag` defined on the 0-based `row` of `file` without checking the file, so
+    /// definitions of the same tag within files inserted afterwards are reported as duplicates.
+    pub fn define_tag(&mut self, tag: &str, file: &str, row: usize) {
+        if !self.tags.contains_key(tag) {
+            self.tags.insert(tag.to_string(), (file.to_string(), row));
+        }
+    }
+
+    /// Records every tag of the help file parsed by `parser` without checking the file, like
+    /// [`Linter::define_tag`].
+    pub fn define_tags(&mut self, parser: &Parser, file: &str) {
+        struct TagVisitor(Vec<(String, usize)>);
+
+        impl Visitor for TagVisitor {
+            type Output = ();
+
+            fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
+                if matches!(ctx.node_type(), Some(NodeType::Tag)) && !ctx.has_error() {
+                    let tag = ctx.node_raw_text().trim().trim_matches('*');
+                    self.0
+                        .push((tag.to_string(), ctx.node().start_position().row));
+                }
+            }
+        }
+
+        let mut visitor = TagVisitor(Vec::new());
+        visitor.visit_all_named(
+            &mut Context::new(parser.src(), &mut parser.tree().walk()),
+            &UNIT_JOINER,
+        );
+        for (tag, row) in visitor.0 {
+            self.define_tag(&tag, file, row);
+        }
+    }
+
+    /// Checks the help file parsed by `parser`, referring to it as `file` within diagnostics.
+    pub fn insert(&mut self, parser: &Parser, file: &str) {
+        let mut visitor = LintVisitor::new(file);
+        visitor.visit_all(
+            &mut Context::new(parser.src(), &mut parser.tree().walk()),
+            &UNIT_JOINER,
+            /* unnamed */ true,
+        );
+
+        for (tag, diagnostic) in visitor.tags {
+            match self.tags.get(&tag) {
+                Some((first_file, first_row)) => self.diagnostics.push(Diagnostic {
+                    rule: LintRule::DuplicateTag,
+                    message: format!(
+                        "Duplicate tag *{tag}*, first defined at {first_file}:{}",
+                        first_row + 1
+                    ),
+                    ..diagnostic
+                }),
+                None => {
+                    self.tags
+                        .insert(tag, (file.to_string(), diagnostic.point.row));
+                }
+            }
+        }
+
+        self.taglinks.extend(visitor.taglinks);
+
+        // Code blocks are ended by a `<` at the start of the line following the code or by a line
+        // that is not indented, so only a code block that reaches the end of the file without a
+        // `<` is unclosed, which usually means it swallowed the modeline
+        let src = parser.src();
+        for (diagnostic, end_row, end_byte) in visitor.codeblocks {
+            let reaches_end = src
+                .get(end_byte..)
+                .map_or(true, |rest| rest.trim().is_empty());
+            if reaches_end && !visitor.code_ends.contains(&end_row) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        let textwidth = match utils::find_modeline(src) {
+            Some((_, options)) => utils::modeline_textwidth(options).unwrap_or(DEFAULT_TEXTWIDTH),
+            None => {
+                let row = src.lines().count().saturating_sub(1);
+                self.diagnostics.push(Diagnostic::new(
+                    file,
+                    src.lines().last().unwrap_or_default(),
+                    tree_sitter::Point { row, column: 0 },
+                    LintRule::MissingModeline,
+                    "Missing modeline, e.g. vim:tw=78:ts=8:ft=help:norl:",
+                ));
+                DEFAULT_TEXTWIDTH
+            }
+        };
+
+        for (row, line) in src.lines().enumerate() {
+            let width = utils::display_width(line);
+            if width > textwidth && !visitor.code_rows.contains(&row) {
+                // Located at the first character that extends beyond the textwidth
+                let column = line
+                    .char_indices()
+                    .find(|(i, c)| utils::display_width(&line[..i + c.len_utf8()]) > textwidth)
+                    .map_or(line.len(), |(i, _)| i);
+                self.diagnostics.push(Diagnostic::new(
+                    file,
+                    line,
+                    tree_sitter::Point { row, column },
+                    LintRule::LineTooLong,
+                    format!("Line is {width} columns, which exceeds textwidth of {textwidth}"),
+                ));
+            }
+        }
+
+        self.diagnostics.extend(visitor.diagnostics);
+        self.files.push(file.to_string());
+    }
+
+    /// Resolves taglinks across all inserted help files and returns every diagnostic, ordered by
+    /// file and then by position.
+    pub fn finish(mut self) -> Vec<Diagnostic> {
+        for (tag, diagnostic) in std::mem::take(&mut self.taglinks) {
+            let is_external = self
+                .index
+                .map_or(false, |index| index.href(&tag, None).is_some());
+            if !self.tags.contains_key(&tag) && !is_external && !utils::ignore_invalid(&tag) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        let order: HashMap<&str, usize> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (file.as_str(), i))
+            .collect();
+        self.diagnostics
+            .sort_by_key(|d| (order.get(d.file.as_str()).copied(), d.line, d.column));
+        self.diagnostics
+    }
+}
+
+/// Collects the tags, taglinks, and code blocks of a single help file alongside the diagnostics
+/// that only require the file itself. Tags, taglinks, and code blocks are paired with the
+/// diagnostic reported at their position should they turn out to be a problem.
+struct LintVisitor<'a> {
+    file: &'a str,
+    tags: Vec<(String, Diagnostic)>,
+    taglinks: Vec<(String, Diagnostic)>,
+
+    /// Each code block paired with the row following its code and the byte where it ends.
+    codeblocks: Vec<(Diagnostic, usize, usize)>,
+
+    /// Rows containing the lines of code within code blocks.
+    code_rows: HashSet<usize>,
+
+    /// Rows with a `<` token.
+    code_ends: HashSet<usize>,
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> LintVisitor<'a> {
+    fn new(file: &'a str) -> Self {
+        Self {
+            file,
+            tags: Vec::new(),
+            taglinks: Vec::new(),
+            codeblocks: Vec::new(),
+            code_rows: HashSet::new(),
+            code_ends: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Visitor for LintVisitor<'a> {
+    type Output = ();
+
+    fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
+        let node = ctx.node();
+        let start = node.start_position();
+
+        // Diagnostics are located within the line where the node starts
+        let src = ctx.src();
+        let file = self.file;
+        let diagnostic = |rule, message: String| {
+            let line_start = src[..node.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = src[line_start..]
+                .find('\n')
+                .map_or(src.len(), |i| line_start + i);
+            Diagnostic::new(file, &src[line_start..line_end], start, rule, message)
+        };
+
+        if node.is_error() {
+            let text = ctx.node_raw_text();
+            if !utils::ignore_parse_error(text) {
+                self.diagnostics.push(diagnostic(
+                    LintRule::ParseError,
+                    format!("Failed to parse {:?}", utils::truncate_str(text, 20)),
+                ));
+            }
+            return;
+        }
+
+        match node.node_type() {
+            Some(NodeType::Tag) if !node.has_error() => {
+                let tag = ctx.node_raw_text().trim().trim_matches('*');
+                let diagnostic =
+                    diagnostic(LintRule::DuplicateTag, format!("Duplicate tag *{tag}*"));
+                self.tags.push((tag.to_string(), diagnostic));
+            }
+            Some(NodeType::Taglink) if !node.has_error() => {
+                let tag = ctx.node_raw_text().trim().trim_matches('|');
+                let diagnostic = diagnostic(
+                    LintRule::UnresolvedTaglink,
+                    format!("Taglink |{tag}| does not match any tag"),
+                );
+                self.taglinks.push((tag.to_string(), diagnostic));
+            }
+            Some(NodeType::H1 | NodeType::H2 | NodeType::H3) => {
+                let is_tagged = (0..node.named_child_count())
+                    .filter_map(|i| node.named_child(i))
+                    .any(|child| matches!(child.node_type(), Some(NodeType::Tag)));
+                if !is_tagged && !utils::is_noise(ctx.node_raw_text()) {
+                    self.diagnostics.push(diagnostic(
+                        LintRule::UntaggedHeading,
+                        String::from("Heading has no *tag* to link to it"),
+                    ));
+                }
+            }
+            Some(NodeType::Codeblock) => {
+                let end = node.end_position();
+                let diagnostic = diagnostic(
+                    LintRule::UnclosedCodeblock,
+                    String::from("Code block runs to the end of the file without a `<` to end it"),
+                );
+                self.codeblocks.push((diagnostic, end.row, node.end_byte()));
+                self.code_rows.extend(start.row + 1..end.row);
+            }
+            None if !node.is_named() && node.kind() == "<" => {
+                self.code_ends.insert(start.row);
+            }
+            _ => {}
+        }
+    }
+}
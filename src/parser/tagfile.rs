@@ -0,0 +1,146 @@
+use crate::{collect_tags, Error, Parser};
+use std::fmt;
+use std::str::FromStr;
+
+/// Header written at the top of a `tags` file whose help files contain utf-8.
+const ENCODING_HEADER: &str = "!_TAG_FILE_ENCODING\tutf-8\t//";
+
+/// Single entry within a Vim `tags` file.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TagsEntry {
+    /// Name of the tag, e.g. `nvim_buf_get_lines()`.
+    pub tag: String,
+
+    /// Help file containing the tag, e.g. `api.txt`.
+    pub file: String,
+
+    /// Ex command used to locate the tag within the file, e.g. `/*nvim_buf_get_lines()*`.
+    pub cmd: String,
+}
+
+impl TagsEntry {
+    /// Creates an entry for `tag` within `file` using the search command produced by
+    /// `:helptags`, escaping `\` and `/` within the tag.
+    pub fn new(tag: impl Into<String>, file: impl Into<String>) -> Self {
+        let tag = tag.into();
+        let mut cmd = String::from("/*");
+        for c in tag.chars() {
+            if c == '\\' || c == '/' {
+                cmd.push('\\');
+            }
+            cmd.push(c);
+        }
+        cmd.push('*');
+
+        Self {
+            tag,
+            file: file.into(),
+            cmd,
+        }
+    }
+}
+
+impl fmt::Display for TagsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.tag, self.file, self.cmd)
+    }
+}
+
+/// Represents a Vim `tags` file as produced by `:helptags`.
+#[derive(Clone, Debug, Default)]
+pub struct TagsFile {
+    /// If true, the `!_TAG_FILE_ENCODING` header is included.
+    pub utf8: bool,
+
+    /// Entries within the file.
+    pub entries: Vec<TagsEntry>,
+}
+
+impl TagsFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds every tag found by the `parser` as belonging to `file`. Like `:helptags`, the file is
+    /// detected as utf-8 if its first line contains non-ASCII characters.
+    pub fn insert_from_parser(&mut self, parser: &Parser, file: &str) {
+        if parser
+            .src()
+            .lines()
+            .next()
+            .map_or(false, |line| !line.is_ascii())
+        {
+            self.utf8 = true;
+        }
+
+        for tag in collect_tags(parser) {
+            self.entries.push(TagsEntry::new(tag, file));
+        }
+    }
+
+    /// Sorts the entries by tag and then file, matching the byte order used by `:helptags`.
+    pub fn sort(&mut self) {
+        self.entries.sort();
+    }
+
+    /// Returns each entry whose tag is the same as the entry before it. Expects the entries to be
+    /// sorted.
+    pub fn duplicates(&self) -> Vec<&TagsEntry> {
+        self.entries
+            .windows(2)
+            .filter(|pair| pair[0].tag == pair[1].tag)
+            .map(|pair| &pair[1])
+            .collect()
+    }
+}
+
+impl fmt::Display for TagsFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.utf8 {
+            writeln!(f, "{ENCODING_HEADER}")?;
+        }
+
+        for entry in self.entries.iter() {
+            writeln!(f, "{entry}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for TagsFile {
+    type Err = Error;
+
+    /// Parses the contents of a `tags` file, skipping any `!_TAG_` metadata lines other than the
+    /// encoding header.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tags_file = TagsFile::new();
+
+        for (i, line) in s.lines().enumerate() {
+            if line == ENCODING_HEADER {
+                tags_file.utf8 = true;
+                continue;
+            } else if line.starts_with("!_TAG_") || line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, '\t');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(tag), Some(file), Some(cmd)) => tags_file.entries.push(TagsEntry {
+                    tag: tag.to_string(),
+                    file: file.to_string(),
+                    cmd: cmd.to_string(),
+                }),
+                _ => {
+                    return Err(Error::TagsFile {
+                        path: None,
+                        row: i + 1,
+                        line: line.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(tags_file)
+    }
+}
@@ -0,0 +1,109 @@
+use super::FromParser;
+use crate::utils;
+use crate::{Context, Error, EventVisitor, NodeExt, NodeType, Parser};
+use serde::Serialize;
+
+/// Heading within a help file, along with any headings nested beneath it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Heading {
+    /// Name of the heading with any tags removed.
+    pub name: String,
+
+    /// Headings nested beneath this heading.
+    pub subheadings: Vec<Heading>,
+
+    /// Tag used to link to the heading, being the first `*tag*` within the heading or, if there
+    /// is none, a tag derived from the heading name.
+    pub tag: String,
+}
+
+impl Heading {
+    /// Creates a heading from the h1, h2, or h3 node being visited.
+    pub fn from_ctx(ctx: &Context<'_, '_, '_>) -> Self {
+        let node = ctx.node();
+        let src = ctx.src().as_bytes();
+        let mut cursor = node.walk();
+        let mut words = Vec::new();
+        let mut tag = None;
+
+        for child in node.named_children(&mut cursor) {
+            let text = child.utf8_text(src).unwrap_or_default().trim();
+            if matches!(child.node_type(), Some(NodeType::Tag)) {
+                if tag.is_none() {
+                    tag = Some(text.trim_matches('*').to_string());
+                }
+            } else {
+                words.push(text);
+            }
+        }
+
+        let name = words.join(" ");
+        let tag = tag.unwrap_or_else(|| utils::to_heading_tag(&name));
+        Self {
+            name,
+            subheadings: Vec::new(),
+            tag,
+        }
+    }
+}
+
+/// Table of contents of a help file, built from its heading hierarchy.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct Toc {
+    pub headings: Vec<Heading>,
+}
+
+impl Toc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a heading into the table of contents. Top-level headings, or any heading when the
+    /// table is empty, are added at the root; otherwise, the heading is nested beneath the last
+    /// top-level heading.
+    pub fn insert(&mut self, heading: Heading, top_level: bool) {
+        match self.headings.last_mut() {
+            Some(parent) if !top_level => parent.subheadings.push(heading),
+            _ => self.headings.push(heading),
+        }
+    }
+
+    /// Returns the most recently inserted heading.
+    pub fn last(&self) -> Option<&Heading> {
+        let heading = self.headings.last()?;
+        Some(heading.subheadings.last().unwrap_or(heading))
+    }
+
+    /// Returns true if the table of contents has no headings.
+    pub fn is_empty(&self) -> bool {
+        self.headings.is_empty()
+    }
+}
+
+impl FromParser for Toc {
+    type Err = Error;
+
+    /// Parses the headings of a help file into a table of contents.
+    fn from_parser(parser: &Parser) -> Result<Self, Self::Err> {
+        struct TocVisitor(Toc);
+
+        impl EventVisitor for TocVisitor {
+            fn enter<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) {
+                let top_level = match ctx.node_type() {
+                    Some(NodeType::H1) => true,
+                    Some(NodeType::H2 | NodeType::H3) => false,
+                    _ => return,
+                };
+
+                if !utils::is_noise(ctx.node_raw_text()) {
+                    self.0.insert(Heading::from_ctx(ctx), top_level);
+                }
+            }
+        }
+
+        let mut visitor = TocVisitor(Toc::new());
+        visitor.walk_named(&mut Context::new(parser.src(), &mut parser.tree().walk()));
+        Ok(visitor.0)
+    }
+}
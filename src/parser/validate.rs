@@ -0,0 +1,136 @@
+use crate::utils;
+use crate::{Context, NodeExt, NodeType, Parser, TagIndex, Visitor, UNIT_JOINER};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Report of problems across help files, matching the result of `validate()` within Neovim's
+/// `gen_help_html.lua` with the addition of the noise lines of each file.
+///
+/// Port of https://github.com/neovim/neovim/blob/99cf111289bfcd14981255e805da43bac5139141/scripts/gen_help_html.lua#L918
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ValidationReport {
+    /// Total help files validated.
+    pub helpfiles: usize,
+
+    /// Total parse errors across all help files.
+    pub err_count: usize,
+
+    /// Taglinks and optionlinks without a matching tag, mapped to the help file of the link.
+    pub invalid_links: BTreeMap<String, String>,
+
+    /// Urls that include trailing punctuation, mapped to the help file of the url.
+    pub invalid_urls: BTreeMap<String, String>,
+
+    /// Text that could not be parsed, grouped by help file.
+    pub parse_errors: BTreeMap<String, Vec<String>>,
+
+    /// Lines that are dropped when converted, grouped by help file.
+    pub noise_lines: BTreeMap<String, Vec<String>>,
+}
+
+impl ValidationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates the help file parsed by `parser`, referring to it as `file` and resolving links
+    /// to tags within the `index`. Known false positives are excluded, see
+    /// [`utils::ignore_invalid`] and [`utils::ignore_parse_error`].
+    pub fn insert(&mut self, parser: &Parser, file: &str, index: &TagIndex) {
+        let mut visitor = ValidateVisitor {
+            file,
+            index,
+            report: self,
+            parse_errors: Vec::new(),
+            noise_lines: Vec::new(),
+        };
+        visitor.visit_all_named(
+            &mut Context::new(parser.src(), &mut parser.tree().walk()),
+            &UNIT_JOINER,
+        );
+
+        let ValidateVisitor {
+            parse_errors,
+            noise_lines,
+            ..
+        } = visitor;
+
+        self.helpfiles += 1;
+        self.err_count += parse_errors.len();
+        if !parse_errors.is_empty() {
+            self.parse_errors.insert(file.to_string(), parse_errors);
+        }
+        if !noise_lines.is_empty() {
+            self.noise_lines.insert(file.to_string(), noise_lines);
+        }
+    }
+
+    /// Returns the parse errors of the `file` that has been validated.
+    pub fn parse_errors_of(&self, file: &str) -> &[String] {
+        self.parse_errors.get(file).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns true if any links, urls, or parse errors are invalid. Noise lines are expected and
+    /// do not count as problems.
+    pub fn has_errors(&self) -> bool {
+        self.err_count > 0 || !self.invalid_links.is_empty() || !self.invalid_urls.is_empty()
+    }
+}
+
+struct ValidateVisitor<'a> {
+    file: &'a str,
+    index: &'a TagIndex,
+    report: &'a mut ValidationReport,
+    parse_errors: Vec<String>,
+    noise_lines: Vec<String>,
+}
+
+impl<'a> Visitor for ValidateVisitor<'a> {
+    type Output = ();
+
+    fn visit<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) -> Self::Output {
+        let node = ctx.node();
+        let text = ctx.node_raw_text();
+
+        if node.is_error() {
+            if !utils::ignore_parse_error(text) {
+                self.parse_errors.push(text.to_string());
+            }
+            return;
+        }
+
+        match node.node_type() {
+            Some(NodeType::Taglink | NodeType::Optionlink) if !node.has_error() => {
+                let tagname = match node.node_type() {
+                    Some(NodeType::Taglink) => text.trim().trim_matches('|'),
+                    _ => text.trim(),
+                };
+                if self.index.href(tagname, None).is_none() && !utils::ignore_invalid(tagname) {
+                    self.report
+                        .invalid_links
+                        .insert(tagname.to_string(), self.file.to_string());
+                }
+            }
+            Some(NodeType::Url) => {
+                let url = text.trim();
+                let (fixed_url, _) = utils::fix_url(url);
+                if fixed_url != url {
+                    self.report
+                        .invalid_urls
+                        .insert(url.to_string(), self.file.to_string());
+                }
+            }
+            // First line is always noise, like the title line of traditional :help pages
+            Some(NodeType::Line)
+                if !matches!(
+                    ctx.parent_node_type(),
+                    Some(NodeType::Code | NodeType::Codeblock)
+                ) && !utils::is_blank(text)
+                    && (self.noise_lines.is_empty() || utils::is_noise(text)) =>
+            {
+                self.noise_lines.push(text.trim_end().to_string());
+            }
+            _ => {}
+        }
+    }
+}
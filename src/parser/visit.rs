@@ -1,3 +1,4 @@
+use crate::utils;
 use std::fmt;
 use std::str::FromStr;
 
@@ -54,6 +55,18 @@ pub const NEWLINE_STRING_JOINER: StringJoiner<'static> = StringJoiner::new("\n")
 /// Instance of [`StringJoiner`] whose separator is ` `.
 pub const SPACE_STRING_JOINER: StringJoiner<'static> = StringJoiner::new(" ");
 
+/// Implementation of [`Joiner`] that discards outputs, used by visitors that only collect state.
+pub struct UnitJoiner;
+
+impl Joiner for UnitJoiner {
+    type Output = ();
+
+    fn join(&self, _outputs: Vec<Self::Output>) -> Self::Output {}
+}
+
+/// Instance of [`UnitJoiner`].
+pub const UNIT_JOINER: UnitJoiner = UnitJoiner;
+
 /// Interface that handles visiting different tree nodes in order to generate some output.
 pub trait Visitor {
     type Output;
@@ -89,12 +102,12 @@ pub trait Visitor {
                 outputs.last_mut().unwrap().push(self.visit(ctx));
             }
 
-            if ctx.cursor.goto_first_child() {
+            if ctx.goto_first_child() {
                 outputs.push(Vec::new());
                 continue;
             }
 
-            if ctx.cursor.goto_next_sibling() {
+            if ctx.goto_next_sibling() {
                 continue;
             }
 
@@ -102,7 +115,7 @@ pub trait Visitor {
             loop {
                 // If no parent left, this means we've gone through the entire tree
                 // and have reached the root node
-                if !ctx.cursor.goto_parent() {
+                if !ctx.goto_parent() {
                     return joiner.join(outputs.into_iter().next().unwrap());
                 }
 
@@ -117,7 +130,7 @@ pub trait Visitor {
 
                 // Otherwise, attempt to go to the next sibling and, if successful,
                 // we are done with this retracing loop
-                if ctx.cursor.goto_next_sibling() {
+                if ctx.goto_next_sibling() {
                     break;
                 }
             }
@@ -134,7 +147,8 @@ pub trait Visitor {
     }
 
     /// Visits all immediate children nodes from the root defined in the [`Context]. The root node
-    /// itself is NOT visited. Returns a collection of outputs.
+    /// itself is NOT visited. Returns a collection of outputs. Once finished, the cursor is
+    /// returned to the root node.
     ///
     /// If `unnamed` is true, then nodes that are unnamed will also be visited.
     fn visit_children<'src, 'tree>(
@@ -144,7 +158,7 @@ pub trait Visitor {
     ) -> Vec<Self::Output> {
         let mut outputs = Vec::new();
 
-        if !ctx.cursor.goto_first_child() {
+        if !ctx.goto_first_child() {
             return outputs;
         }
 
@@ -153,17 +167,93 @@ pub trait Visitor {
                 outputs.push(self.visit(ctx));
             }
 
-            if !ctx.cursor.goto_next_sibling() {
+            if !ctx.goto_next_sibling() {
+                // Restore the cursor to the root so the caller can continue to inspect it
+                ctx.goto_parent();
                 return outputs;
             }
         }
     }
 }
 
+/// Interface that handles entering and leaving different tree nodes. Each node is entered before
+/// any of its children and left after all of its children, which lets implementations track
+/// state that spans a node's descendants such as nesting.
+pub trait EventVisitor {
+    /// Called when entering a node, before any of its children are entered.
+    fn enter<'src, 'tree>(&mut self, _ctx: &mut Context<'src, 'tree, '_>) {}
+
+    /// Called when leaving a node, after all of its children have been left.
+    fn leave<'src, 'tree>(&mut self, _ctx: &mut Context<'src, 'tree, '_>) {}
+
+    /// Walks all named nodes starting with the root defined in the given [`Context`], entering
+    /// and leaving each of them.
+    fn walk_named<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>) {
+        self.walk(ctx, /* unnamed */ false)
+    }
+
+    /// Walks all nodes starting with the root defined in the given [`Context`], entering and
+    /// leaving each of them. Once finished, the cursor is returned to the root node.
+    ///
+    /// If `unnamed` is true, then nodes that are unnamed will also be entered and left.
+    fn walk<'src, 'tree>(&mut self, ctx: &mut Context<'src, 'tree, '_>, unnamed: bool) {
+        let root_depth = ctx.depth();
+
+        loop {
+            if ctx.node().is_named() || unnamed {
+                self.enter(ctx);
+            }
+
+            if ctx.goto_first_child() {
+                continue;
+            }
+
+            // Leave nodes until one has a next sibling, stopping once the root is left
+            loop {
+                if ctx.node().is_named() || unnamed {
+                    self.leave(ctx);
+                }
+
+                if ctx.depth() == root_depth {
+                    return;
+                }
+
+                if ctx.goto_next_sibling() {
+                    break;
+                }
+
+                ctx.goto_parent();
+            }
+        }
+    }
+}
+
 /// Maintains context throughout visiting nodes in a tree.
 pub struct Context<'src, 'tree, 'cursor> {
-    pub(super) src: &'src str,
-    pub(super) cursor: &'cursor mut tree_sitter::TreeCursor<'tree>,
+    src: &'src str,
+    cursor: &'cursor mut tree_sitter::TreeCursor<'tree>,
+
+    /// Types of the ancestors of the node being visited, starting with the root of the tree.
+    ancestors: Vec<Option<NodeType>>,
+}
+
+impl<'src, 'tree, 'cursor> Context<'src, 'tree, 'cursor> {
+    /// Creates a context to visit the tree of `src` starting at the node of the `cursor`.
+    pub fn new(src: &'src str, cursor: &'cursor mut tree_sitter::TreeCursor<'tree>) -> Self {
+        let mut ancestors = Vec::new();
+        let mut parent = cursor.node().parent();
+        while let Some(node) = parent {
+            ancestors.push(node.node_type());
+            parent = node.parent();
+        }
+        ancestors.reverse();
+
+        Self {
+            src,
+            cursor,
+            ancestors,
+        }
+    }
 }
 
 impl<'src, 'tree> Context<'src, 'tree, '_> {
@@ -200,9 +290,7 @@ impl<'src, 'tree> Context<'src, 'tree, '_> {
     /// Cleans the provided text.
     #[inline]
     pub fn clean_text(&self, s: &str) -> String {
-        s.replace('&', "&amp;")
-            .replace('<', "&lt;")
-            .replace('>', "&gt;")
+        utils::escape_html(s)
     }
 
     /// Returns true if the node being visited is an error or has errors in children nodes or
@@ -218,6 +306,56 @@ impl<'src, 'tree> Context<'src, 'tree, '_> {
     pub fn has_children(&self) -> bool {
         self.node().named_child_count() > 0
     }
+
+    /// Returns the depth of the node being visited in the tree where 0 is the root of the tree.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.ancestors.len()
+    }
+
+    /// Returns the types of the ancestors of the node being visited, starting with the root of
+    /// the tree and ending with the parent of the node.
+    #[inline]
+    pub fn ancestors(&self) -> &[Option<NodeType>] {
+        &self.ancestors
+    }
+
+    /// Returns vimdoc type associated with the parent of the node being visited.
+    #[inline]
+    pub fn parent_node_type(&self) -> Option<NodeType> {
+        self.ancestors.last().copied().flatten()
+    }
+
+    /// Returns true if any ancestor of the node being visited is of the given type.
+    #[inline]
+    pub fn is_within(&self, ty: NodeType) -> bool {
+        self.ancestors.contains(&Some(ty))
+    }
+
+    /// Moves to the first child of the node being visited, returning false if it has no children.
+    pub fn goto_first_child(&mut self) -> bool {
+        let ty = self.node_type();
+        let moved = self.cursor.goto_first_child();
+        if moved {
+            self.ancestors.push(ty);
+        }
+        moved
+    }
+
+    /// Moves to the next sibling of the node being visited, returning false if there is none.
+    #[inline]
+    pub fn goto_next_sibling(&mut self) -> bool {
+        self.cursor.goto_next_sibling()
+    }
+
+    /// Moves to the parent of the node being visited, returning false if it is the root.
+    pub fn goto_parent(&mut self) -> bool {
+        let moved = self.cursor.goto_parent();
+        if moved {
+            self.ancestors.pop();
+        }
+        moved
+    }
 }
 
 /// Interface providing additional methods for [`tree_sitter::Node`].
@@ -269,6 +407,7 @@ impl NodeExt for tree_sitter::Node<'_> {
 }
 
 /// Represents types of nodes that can be encountered when navigating a vimdoc.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum NodeType {
     Argument,
@@ -0,0 +1,342 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use vimdoc2html::{escape_html, url_encode_path};
+
+use super::{find_files, Failures};
+
+/// Path of the event stream that tells open pages to reload.
+pub const LIVE_RELOAD_PATH: &str = "/__livereload";
+
+/// How long writing to an open page may block before the page is forgotten, so a stalled
+/// browser cannot hold up reloading the others.
+const LIVE_RELOAD_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Returns the script included within the `<head>` of served pages, reloading the page when the
+/// site is converted again.
+pub fn live_reload_script() -> String {
+    format!(
+        r#"<script>new EventSource("{LIVE_RELOAD_PATH}").onmessage = function () {{ location.reload(); }};</script>"#
+    )
+}
+
+/// Pages that are open in a browser, listening for the site to be converted again.
+#[derive(Clone, Default)]
+pub struct LiveReload(Arc<Mutex<Vec<TcpStream>>>);
+
+impl LiveReload {
+    /// Tells every open page to reload, forgetting those that have since been closed.
+    pub fn reload(&self) {
+        let mut streams = self.0.lock().unwrap();
+        streams.retain_mut(|stream| {
+            stream
+                .write_all(b"data: reload\n\n")
+                .and_then(|_| stream.flush())
+                .is_ok()
+        });
+    }
+}
+
+/// Serves the files within `root` to connections accepted by the `listener`, handling each
+/// connection on its own thread until the process exits.
+pub fn serve(listener: TcpListener, root: PathBuf, live_reload: LiveReload) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let root = root.clone();
+        let live_reload = live_reload.clone();
+        std::thread::spawn(move || {
+            // Failing to respond only means that the browser went away
+            let _ = handle(stream, &root, &live_reload);
+        });
+    }
+}
+
+/// Responds to a single HTTP request on the `stream`.
+fn handle(mut stream: TcpStream, root: &Path, live_reload: &LiveReload) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Headers are not needed, but are read so the browser does not see the connection reset
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return respond(&mut stream, "400 Bad Request", "text/plain", b"Bad request");
+    };
+    if method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method not allowed",
+        );
+    }
+
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    if path == LIVE_RELOAD_PATH {
+        stream.set_write_timeout(Some(LIVE_RELOAD_WRITE_TIMEOUT))?;
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\n\
+            Content-Type: text/event-stream\r\n\
+            Cache-Control: no-cache\r\n\
+            Connection: keep-alive\r\n\r\n",
+        )?;
+        live_reload.0.lock().unwrap().push(stream);
+        return Ok(());
+    }
+
+    let Some(mut file) = resolve(root, path) else {
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not found");
+    };
+    if file.is_dir() {
+        file = file.join("index.html");
+        if !file.is_file() && path.trim_matches('/').is_empty() {
+            let body = index_page(root);
+            return respond(&mut stream, "200 OK", "text/html", body.as_bytes());
+        }
+    }
+
+    match std::fs::read(&file) {
+        Ok(body) => respond(&mut stream, "200 OK", content_type(&file), &body),
+        Err(_) => respond(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+    }
+}
+
+/// Writes a complete response with the `body` to the `stream`.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+        Content-Type: {content_type}; charset=utf-8\r\n\
+        Content-Length: {}\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Returns the file within `root` requested by the url `path`, or none if the path would escape
+/// the root.
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(percent_decode(path.trim_start_matches('/')));
+    let is_within_root = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    is_within_root.then(|| root.join(relative))
+}
+
+/// Returns a page linking to every HTML page within `root`, used when the site has no index.
+fn index_page(root: &Path) -> String {
+    let pages = find_files(
+        vec![root.to_path_buf()],
+        &[String::from("html")],
+        true,
+        &mut Failures::default(),
+    );
+    let links: String = pages
+        .iter()
+        .filter_map(|page| page.strip_prefix(root).ok())
+        .map(|page| {
+            let name = super::page_name(page);
+            format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                url_encode_path(&name),
+                escape_html(&name)
+            )
+        })
+        .collect();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>vimdoc</title>\n\
+        {}\n</head>\n<body>\n<ul>\n{links}</ul>\n</body>\n</html>\n",
+        live_reload_script()
+    )
+}
+
+/// Returns the content type of the file at `path` based on its extension.
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        _ => "text/plain",
+    }
+}
+
+/// Decodes `%XX` escapes within a url path, keeping invalid escapes as is.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// Site being served from a directory that is removed once the test is done.
+    struct Site {
+        dir: PathBuf,
+        root: PathBuf,
+        addr: std::net::SocketAddr,
+        live_reload: LiveReload,
+    }
+
+    impl Site {
+        /// Serves a site named after the test, holding the `pages` within its root and a secret
+        /// file next to the root that must never be served.
+        fn new(name: &str, pages: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("vimdoc2html-serve-{name}-{}", std::process::id()));
+            let root = dir.join("site");
+            std::fs::create_dir_all(&root).unwrap();
+            std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+            for (page, body) in pages {
+                std::fs::write(root.join(page), body).unwrap();
+            }
+
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let live_reload = LiveReload::default();
+            std::thread::spawn({
+                let root = root.clone();
+                let live_reload = live_reload.clone();
+                move || serve(listener, root, live_reload)
+            });
+
+            Self {
+                dir,
+                root,
+                addr,
+                live_reload,
+            }
+        }
+
+        /// Requests the `target` and returns the full response.
+        fn get(&self, target: &str) -> String {
+            let mut stream = TcpStream::connect(self.addr).unwrap();
+            write!(stream, "GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        }
+    }
+
+    impl Drop for Site {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn should_serve_page() {
+        let site = Site::new("page", &[("test.html", "<p>Hello</p>")]);
+        let response = site.get("/test.html");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("Content-Type: text/html"), "{response}");
+        assert!(response.ends_with("\r\n\r\n<p>Hello</p>"), "{response}");
+    }
+
+    #[test]
+    fn should_respond_not_found_for_missing_page() {
+        let site = Site::new("missing", &[]);
+        let response = site.get("/missing.html");
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+    }
+
+    #[test]
+    fn should_reject_paths_outside_root() {
+        let site = Site::new("traversal", &[]);
+        assert!(site.root.join("../secret.txt").is_file());
+        for target in [
+            "/../secret.txt",
+            "/%2e%2e/secret.txt",
+            "/a/../../secret.txt",
+        ] {
+            let response = site.get(target);
+            assert!(
+                response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+                "{target}: {response}"
+            );
+            assert!(!response.contains("secret"), "{target}: {response}");
+        }
+    }
+
+    #[test]
+    fn should_escape_names_in_index() {
+        let site = Site::new("index", &[("a&b <c>.html", "")]);
+        let response = site.get("/");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(
+            response.contains(r#"<a href="a%26b%20%3Cc%3E.html">a&amp;b &lt;c&gt;.html</a>"#),
+            "{response}"
+        );
+        assert!(response.contains(&live_reload_script()), "{response}");
+    }
+
+    #[test]
+    fn should_send_reload_to_open_pages() {
+        let site = Site::new("reload", &[]);
+        let mut stream = TcpStream::connect(site.addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        write!(stream, "GET {LIVE_RELOAD_PATH} HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "HTTP/1.1 200 OK\r\n");
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+
+        // The page is registered on the server's thread after the headers are written
+        while site.live_reload.0.lock().unwrap().is_empty() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        site.live_reload.reload();
+
+        let mut event = String::new();
+        reader.read_line(&mut event).unwrap();
+        assert_eq!(event, "data: reload\n");
+    }
+}
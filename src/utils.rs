@@ -66,6 +66,19 @@ pub fn ignore_invalid(s: &str) -> bool {
     EXCLUDE_INVALID.contains_key(s) || s.contains("===") || s.contains("---")
 }
 
+/// Escapes the characters `&`, `<`, and `>` so the str can be included within html.
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes the str like [`escape_html`] along with `"`, so it can be included within a
+/// double-quoted html attribute.
+pub fn escape_html_attribute(s: &str) -> String {
+    escape_html(s).replace('"', "&quot;")
+}
+
 /// Returns true if str is entirely comprised of tabs and/or spaces.
 pub fn is_blank(s: &str) -> bool {
     s.chars().all(|c| c == '\t' || c == ' ')
@@ -103,6 +116,68 @@ pub fn is_noise(s: &str) -> bool {
         || MODELINE_RE.is_match(s)
 }
 
+/// Returns the tag on the first line of a traditional :help page, which names the help file.
+/// Example: "api.txt" for "*api.txt*    Nvim"
+pub fn first_line_tag(s: &str) -> Option<&str> {
+    static FIRST_LINE_TAG_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"^\s*\*([^*\s]+)\*"#).unwrap());
+
+    let line = s.lines().next()?;
+    FIRST_LINE_TAG_RE
+        .captures(line)
+        .and_then(|captures| captures.get(1))
+        .map(|m| m.as_str())
+}
+
+/// Returns the index and options of the modeline within the first or last 5 lines of the str,
+/// mirroring the default of Vim's 'modelines'.
+/// Example: "tw=78:ts=8:ft=help:norl:" for "vim:tw=78:ts=8:ft=help:norl:"
+///
+/// Like Vim, `vi:`, `vim:`, and `Vim:` may start a line or follow whitespace, while `ex:` must
+/// follow whitespace. To avoid mistaking prose like "ex: see below" for a modeline, the options
+/// must be a list of option names or `name=value` settings that either sets a value or, without
+/// `set`, ends with `:`.
+pub fn find_modeline(s: &str) -> Option<(usize, &str)> {
+    static MODELINE_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"(?:(?:^|\s)(?:vi|vim(?:[<=>]?\d+)?|Vim)|\sex):\s*(?:set?\s+([^:]*):|(.*)$)"#)
+            .unwrap()
+    });
+    static OPTION_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"^(?:no|inv)?[a-z]+(?:[-+^]?=\S*)?$"#).unwrap());
+
+    let is_modeline = |options: &str, is_set: bool| {
+        let mut items = options.split([':', ' ', '\t']).filter(|x| !x.is_empty());
+        let is_options = items.clone().next().is_some() && items.all(|x| OPTION_RE.is_match(x));
+        is_options && (is_set || options.contains('=') || options.trim_end().ends_with(':'))
+    };
+
+    let lines: Vec<&str> = s.lines().collect();
+    let last = lines.len().saturating_sub(5).max(5.min(lines.len()));
+    (0..5.min(lines.len()))
+        .chain(last..lines.len())
+        .find_map(|i| {
+            let captures = MODELINE_RE.captures(lines[i])?;
+            let (options, is_set) = match (captures.get(1), captures.get(2)) {
+                (Some(options), _) => (options.as_str(), true),
+                (None, Some(options)) => (options.as_str(), false),
+                (None, None) => return None,
+            };
+            is_modeline(options, is_set).then_some((i, options))
+        })
+}
+
+/// Returns the 'textwidth' set within the options of a modeline.
+/// Example: 78 for "tw=78:ts=8:ft=help:norl:"
+pub fn modeline_textwidth(options: &str) -> Option<usize> {
+    static TEXTWIDTH_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"(?:^|[:\s])(?:tw|textwidth)=(\d+)"#).unwrap());
+
+    TEXTWIDTH_RE
+        .captures(options)
+        .and_then(|captures| captures.get(1))
+        .and_then(|m| m.as_str().parse().ok())
+}
+
 // Port of Lua
 // https://github.com/neovim/neovim/blob/6ba34e21fee2a81677e8261dfeaf24c8cd320500/scripts/gen_help_html.lua#L155
 pub fn fix_url(url: &str) -> (&str, &str) {
@@ -116,6 +191,14 @@ pub fn fix_url(url: &str) -> (&str, &str) {
     url.split_at(url.len() - remaining_len)
 }
 
+/// Returns the width of the str when displayed, expanding tabs to the next multiple of 8 columns.
+pub fn display_width(s: &str) -> usize {
+    s.chars().fold(0, |width, c| match c {
+        '\t' => width + 8 - width % 8,
+        _ => width + 1,
+    })
+}
+
 /// Removes leading whitespace from each line to match furthest-left line. Will convert tabs to
 /// `tab_to_space_cnt` spaces.
 pub fn trim_indent(s: &str, tab_to_space_cnt: usize) -> String {
@@ -131,3 +214,64 @@ pub fn trim_indent(s: &str, tab_to_space_cnt: usize) -> String {
         .collect::<Vec<String>>()
         .join("\n")
 }
+
+/// Percent-encodes a str for use within a url, leaving `'`, `(`, and `)` unescaped.
+///
+/// Port of https://github.com/neovim/neovim/blob/99cf111289bfcd14981255e805da43bac5139141/scripts/gen_help_html.lua#L124
+pub fn url_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'_'
+            | b'.'
+            | b'~'
+            | b'!'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')'
+            | b'-' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes a `/`-separated path for use within a url like [`url_encode`], leaving the `/`
+/// between its components unescaped.
+pub fn url_encode_path(s: &str) -> String {
+    s.split('/').map(url_encode).collect::<Vec<_>>().join("/")
+}
+
+/// Converts the name of a heading into a tag, prepending `_` to avoid conflicts with actual
+/// :help tags.
+///
+/// Port of https://github.com/neovim/neovim/blob/99cf111289bfcd14981255e805da43bac5139141/scripts/gen_help_html.lua#L150
+pub fn to_heading_tag(s: &str) -> String {
+    static WHITESPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\s+"#).unwrap());
+    format!("_{}", WHITESPACE_RE.replace_all(s, "-").to_lowercase())
+}
+
+/// Returns the url of the page `to` relative to the page `from`, where both are `/`-separated
+/// paths from the same root. Returns an empty str if both refer to the same page.
+pub fn relative_url(from: &str, to: &str) -> String {
+    if from == to {
+        return String::new();
+    }
+
+    let from_dirs: Vec<&str> = from.split('/').collect();
+    let from_dirs = &from_dirs[..from_dirs.len() - 1];
+    let to_parts: Vec<&str> = to.split('/').collect();
+    let common = from_dirs
+        .iter()
+        .zip(to_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts = vec![".."; from_dirs.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}